@@ -0,0 +1,90 @@
+//! Procedural macros for `pba-qualifier-exam`. They are re-exported by the main crate, which is
+//! where they should be used from.
+
+use proc_macro::{Spacing, TokenStream, TokenTree};
+
+/// Marks a fuel as renewable, by implementing `IsRenewable` for it next to its definition.
+///
+/// With `#[renewable(score = N)]`, it also implements `RenewabilityScore` with a score of `N`,
+/// which must be at least `RENEWABLE_THRESHOLD` for the fuel to count as renewable. The fuel still
+/// needs its own `Fuel` impl.
+///
+/// Only non-generic structs and enums are supported.
+#[proc_macro_attribute]
+pub fn renewable(attr: TokenStream, item: TokenStream) -> TokenStream {
+	let impls = match parse_score(attr).and_then(|score| Ok((name_of(item.clone())?, score))) {
+		Ok((name, score)) => renewable_impls(&name, score),
+		Err(message) => format!("compile_error!({message:?});"),
+	};
+
+	let mut output = item;
+	output.extend(
+		impls
+			.parse::<TokenStream>()
+			.expect("generated code is valid"),
+	);
+	output
+}
+
+/// Parse the optional `score = N` argument of `#[renewable]`.
+fn parse_score(attr: TokenStream) -> Result<Option<u8>, String> {
+	let tokens = attr.into_iter().collect::<Vec<_>>();
+	match tokens.as_slice() {
+		[] => Ok(None),
+		[TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Literal(value)]
+			if key.to_string() == "score"
+				&& eq.as_char() == '='
+				&& eq.spacing() == Spacing::Alone =>
+		{
+			let value = value.to_string();
+			let score = value
+				.trim_end_matches("u8")
+				.parse::<u8>()
+				.map_err(|_| format!("expected a score between 0 and 100, found `{value}`"))?;
+			if score > 100 {
+				return Err(format!("a score cannot exceed 100, found {score}"));
+			}
+			Ok(Some(score))
+		}
+		_ => Err("expected `#[renewable]` or `#[renewable(score = N)]`".to_string()),
+	}
+}
+
+/// The name of the struct or enum defined by `item`.
+fn name_of(item: TokenStream) -> Result<String, String> {
+	let mut tokens = item.into_iter();
+	while let Some(token) = tokens.next() {
+		match token {
+			TokenTree::Ident(keyword)
+				if ["struct", "enum"].contains(&keyword.to_string().as_str()) =>
+			{
+				let Some(TokenTree::Ident(name)) = tokens.next() else {
+					break;
+				};
+				return match tokens.next() {
+					Some(TokenTree::Punct(p)) if p.as_char() == '<' => {
+						Err("`#[renewable]` does not support generic fuels".to_string())
+					}
+					_ => Ok(name.to_string()),
+				};
+			}
+			_ => {}
+		}
+	}
+	Err("`#[renewable]` can only be applied to a struct or an enum".to_string())
+}
+
+fn renewable_impls(name: &str, score: Option<u8>) -> String {
+	let module = "::pba_qualifier_exam::h_advanced_traits";
+	let mut impls = format!("impl {module}::IsRenewable for {name} {{}}");
+	if let Some(score) = score {
+		impls += &format!(
+			"impl {module}::RenewabilityScore for {name} {{ const SCORE: u8 = {score}; }}
+			const _: () = assert!(
+				{score} >= {module}::RENEWABLE_THRESHOLD,
+				\"the score of a renewable fuel must be at least RENEWABLE_THRESHOLD\",
+			);"
+		);
+	}
+	impls
+}
@@ -0,0 +1,84 @@
+//! Runs the energy simulation described by a scenario file, see `pba_qualifier_exam::scenario` for
+//! its format.
+//!
+//! ```text
+//! sim run scenario.toml --ticks 100 --out results.csv
+//! ```
+
+use pba_qualifier_exam::csv::ToCsv;
+use pba_qualifier_exam::scenario::{Scenario, DEFAULT_TICKS};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: sim run <scenario.toml> [--ticks <n>] [--out <results.csv>]";
+
+struct Args {
+	scenario: String,
+	/// Overrides the ticks of the scenario.
+	ticks: Option<u64>,
+	out: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+	if args.next().as_deref() != Some("run") {
+		return Err(USAGE.to_string());
+	}
+	let scenario = args.next().ok_or_else(|| USAGE.to_string())?;
+	let mut parsed = Args {
+		scenario,
+		ticks: None,
+		out: None,
+	};
+
+	while let Some(flag) = args.next() {
+		let value = args
+			.next()
+			.ok_or_else(|| format!("missing value for `{flag}`\n{USAGE}"))?;
+		match flag.as_str() {
+			"--ticks" => {
+				parsed.ticks = Some(
+					value
+						.parse()
+						.map_err(|_| format!("invalid number of ticks `{value}`"))?,
+				)
+			}
+			"--out" => parsed.out = Some(value),
+			_ => return Err(format!("unknown option `{flag}`\n{USAGE}")),
+		}
+	}
+	Ok(parsed)
+}
+
+fn run(args: Args) -> Result<(), String> {
+	let scenario = Scenario::load(&args.scenario).map_err(|e| format!("{}: {e}", args.scenario))?;
+	let mut simulation = scenario
+		.build()
+		.map_err(|e| format!("{}: {e}", args.scenario))?;
+	simulation.run(args.ticks.or(scenario.ticks).unwrap_or(DEFAULT_TICKS));
+
+	if let Some(path) = &args.out {
+		let file = std::fs::File::create(path).map_err(|e| format!("{path}: {e}"))?;
+		simulation
+			.history()
+			.to_csv(std::io::BufWriter::new(file))
+			.map_err(|e| format!("{path}: {e}"))?;
+	}
+
+	let summary = simulation.summary();
+	println!("ticks:            {}", summary.ticks);
+	println!("demand:           {} BTU", summary.demand);
+	println!("total energy:     {} BTU", summary.supplied);
+	println!("unserved demand:  {} BTU", summary.unserved);
+	println!("emissions:        {} g CO2", summary.emissions);
+	println!("stock-outs:       {}", summary.stock_outs);
+	Ok(())
+}
+
+fn main() -> ExitCode {
+	match parse_args(std::env::args().skip(1)).and_then(run) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(message) => {
+			eprintln!("{message}");
+			ExitCode::FAILURE
+		}
+	}
+}
@@ -0,0 +1,31 @@
+//! In this module, results are exported as CSV, such that they can be dropped straight into a
+//! spreadsheet or a plotting tool.
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// Something that can be written as CSV: a header line, followed by one line per record.
+pub trait ToCsv {
+	fn to_csv(&self, out: impl Write) -> io::Result<()>;
+}
+
+/// Quote `field` if it contains a separator, a quote or a line break, doubling its quotes.
+pub fn escape(field: &str) -> Cow<'_, str> {
+	if field.contains([',', '"', '\n', '\r']) {
+		Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+	} else {
+		Cow::Borrowed(field)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn escaping() {
+		assert_eq!(escape("coal"), "coal");
+		assert_eq!(escape("coal, unit 2"), "\"coal, unit 2\"");
+		assert_eq!(escape("the \"big\" one"), "\"the \"\"big\"\" one\"");
+	}
+}
@@ -0,0 +1,1996 @@
+//! In this module, the fuels and energy providers of [`crate::h_advanced_traits`] are put together
+//! into a power grid: a fleet of plants, each burning fuel from the inventories of the grid, that
+//! is dispatched to meet some demand.
+
+use crate::csv::{escape, ToCsv};
+use crate::h_advanced_traits::{
+	DynProvideEnergy, Fuel, FuelContainer, ProvideEnergy, ProvisionError, RuntimeFuel, BTU,
+};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+#[cfg(feature = "snapshot")]
+mod snapshot;
+#[cfg(feature = "snapshot")]
+pub use snapshot::SnapshotError;
+
+/// A fuel described at runtime by its name and energy density, such that fuels of different types
+/// can be stocked side by side.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DynamicFuel {
+	name: String,
+	density: BTU,
+	cents_per_kbtu: u32,
+	grams_per_kbtu: u32,
+}
+
+impl DynamicFuel {
+	pub fn new(name: impl Into<String>, density: BTU) -> Self {
+		Self {
+			name: name.into(),
+			density,
+			cents_per_kbtu: 0,
+			grams_per_kbtu: 0,
+		}
+	}
+
+	/// Set the price of the fuel, see [`crate::market::FuelCost`].
+	pub fn with_cost(mut self, cents_per_kbtu: u32) -> Self {
+		self.cents_per_kbtu = cents_per_kbtu;
+		self
+	}
+
+	/// Set the CO2 emitted by the fuel, see [`crate::h_advanced_traits::CarbonIntensity`].
+	pub fn with_carbon_intensity(mut self, grams_per_kbtu: u32) -> Self {
+		self.grams_per_kbtu = grams_per_kbtu;
+		self
+	}
+
+	/// The runtime description of `F`.
+	pub fn of<F: Fuel>() -> Self {
+		Self::new(F::name(), F::energy_density().into())
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// The energy density, in BTU per unit.
+	pub fn density(&self) -> BTU {
+		self.density
+	}
+
+	/// The price, in cents per thousand BTU. Free unless set with [`DynamicFuel::with_cost`].
+	pub fn cents_per_kbtu(&self) -> u32 {
+		self.cents_per_kbtu
+	}
+
+	/// The CO2 emitted, in grams per thousand BTU. None unless set with
+	/// [`DynamicFuel::with_carbon_intensity`].
+	pub fn grams_per_kbtu(&self) -> u32 {
+		self.grams_per_kbtu
+	}
+}
+
+/// A percentage, e.g. an efficiency. Like all efficiencies in this crate, it saturates at 100.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Percent(u8);
+
+impl Percent {
+	pub const fn new(percent: u8) -> Self {
+		Self(if percent > 100 { 100 } else { percent })
+	}
+
+	pub fn get(self) -> u8 {
+		self.0
+	}
+
+	/// This percentage of `energy`, rounded down.
+	pub fn of(self, energy: BTU) -> BTU {
+		(energy as u64 * self.0 as u64 / 100) as BTU
+	}
+}
+
+/// Energy storage, e.g. a battery, that absorbs the surplus of a [`PowerGrid`] and covers its
+/// shortfalls.
+///
+/// All losses of a round-trip happen while charging: only `efficiency` percent of the energy put in
+/// is stored, and all of the stored energy can be taken out again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Storage {
+	capacity: BTU,
+	stored: BTU,
+	efficiency: Percent,
+	losses: u64,
+}
+
+impl Storage {
+	/// An empty storage.
+	pub fn new(capacity: BTU, efficiency: Percent) -> Self {
+		Self {
+			capacity,
+			stored: 0,
+			efficiency,
+			losses: 0,
+		}
+	}
+
+	pub fn capacity(&self) -> BTU {
+		self.capacity
+	}
+
+	/// The energy that can currently be discharged.
+	pub fn stored(&self) -> BTU {
+		self.stored
+	}
+
+	/// The round-trip efficiency.
+	pub fn efficiency(&self) -> Percent {
+		self.efficiency
+	}
+
+	/// The lifetime energy lost while charging.
+	pub fn losses(&self) -> u64 {
+		self.losses
+	}
+
+	/// Put up to `energy` into the storage, and return how much of it was taken. Nothing more is
+	/// taken once the storage is full, and nothing at all if its efficiency is zero.
+	pub fn charge(&mut self, energy: BTU) -> BTU {
+		let efficiency = self.efficiency.get() as u64;
+		if efficiency == 0 {
+			return 0;
+		}
+		let headroom = (self.capacity - self.stored) as u64;
+		let taken = (energy as u64).min(headroom * 100 / efficiency) as BTU;
+		let gained = self.efficiency.of(taken);
+		self.stored += gained;
+		self.losses += (taken - gained) as u64;
+		taken
+	}
+
+	/// Take up to `energy` out of the storage, and return how much was available.
+	pub fn discharge(&mut self, energy: BTU) -> BTU {
+		let taken = energy.min(self.stored);
+		self.stored -= taken;
+		taken
+	}
+}
+
+/// The stock of a single fuel, along with the deliveries of it that are scheduled, by tick.
+///
+/// ```
+/// use pba_qualifier_exam::grid::*;
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let mut coal = Inventory::<Coal>::new(10).with_delivery(2, 50);
+/// assert!(coal.try_take(20).is_none());
+/// assert_eq!(coal.try_take(10).map(|c| c.amount()), Some(10));
+///
+/// assert_eq!(coal.receive(1), 0);
+/// assert_eq!(coal.receive(2), 50);
+/// assert_eq!(coal.amount(), 50);
+/// # }
+/// ```
+pub struct Inventory<F: Fuel> {
+	stock: FuelContainer<F>,
+	deliveries: BTreeMap<u64, u32>,
+}
+
+impl<F: Fuel> Inventory<F> {
+	pub fn new(amount: u32) -> Self {
+		Self {
+			stock: FuelContainer::new(amount),
+			deliveries: BTreeMap::new(),
+		}
+	}
+
+	/// Schedule a delivery of `amount` units at `tick`, on top of those already scheduled. The
+	/// deliveries of a tick saturate at `u32::MAX` units.
+	pub fn schedule(&mut self, tick: u64, amount: u32) {
+		let scheduled = self.deliveries.entry(tick).or_default();
+		*scheduled = scheduled.saturating_add(amount);
+	}
+
+	/// Same as [`Inventory::schedule`], for building up an inventory.
+	pub fn with_delivery(mut self, tick: u64, amount: u32) -> Self {
+		self.schedule(tick, amount);
+		self
+	}
+
+	/// The amount of fuel in stock, not counting the deliveries yet to come.
+	pub fn amount(&self) -> u32 {
+		self.stock.amount()
+	}
+
+	/// The deliveries yet to come, by tick.
+	pub fn deliveries(&self) -> &BTreeMap<u64, u32> {
+		&self.deliveries
+	}
+
+	/// Receive all deliveries scheduled up to `tick`, and return the amount received. The stock
+	/// saturates at `u32::MAX` units.
+	pub fn receive(&mut self, tick: u64) -> u32 {
+		let received = take_due(&mut self.deliveries, tick);
+		self.add(received);
+		received
+	}
+
+	/// Add `amount` units to the stock right away, up to `u32::MAX` units.
+	fn add(&mut self, amount: u32) {
+		self.stock = FuelContainer::new(self.stock.amount().saturating_add(amount));
+	}
+
+	/// Take `amount` units out of the stock, unless there is not enough of it.
+	pub fn try_take(&mut self, amount: u32) -> Option<FuelContainer<F>> {
+		self.stock.try_take(amount)
+	}
+}
+
+/// Remove all deliveries scheduled up to `tick` from `deliveries`, and add them up, up to
+/// `u32::MAX`.
+fn take_due(deliveries: &mut BTreeMap<u64, u32>, tick: u64) -> u32 {
+	let later = match tick.checked_add(1) {
+		Some(next) => deliveries.split_off(&next),
+		None => BTreeMap::new(),
+	};
+	std::mem::replace(deliveries, later)
+		.into_values()
+		.fold(0, u32::saturating_add)
+}
+
+/// Some fuel in the inventories of a [`PowerGrid`].
+struct Stock {
+	fuel: DynamicFuel,
+	inventory: Inventory<RuntimeFuel>,
+}
+
+/// A plant of a [`PowerGrid`].
+struct Plant {
+	name: String,
+	provider: Box<dyn DynProvideEnergy>,
+	/// The index of the stock the plant burns from.
+	stock: usize,
+	/// The most fuel the plant can burn in a single dispatch.
+	max_fuel: u32,
+}
+
+/// What a [`DispatchStrategy`] knows about a plant of a [`PowerGrid`].
+#[derive(Clone, Copy, Debug)]
+pub struct PlantInfo<'a> {
+	pub name: &'a str,
+	pub fuel: &'a DynamicFuel,
+	/// The fuel left in the inventories for this plant, which may be shared with other plants.
+	pub available_fuel: u32,
+	/// The most fuel the plant can burn in a single dispatch.
+	pub max_fuel: u32,
+	/// The current efficiency of the plant, in percent.
+	pub efficiency: u8,
+}
+
+impl PlantInfo<'_> {
+	/// The cost of the energy produced by the plant, in cents per thousand BTU.
+	pub fn marginal_cost(&self) -> u32 {
+		per_useful_kbtu(self.fuel.cents_per_kbtu(), self.efficiency)
+	}
+
+	/// The CO2 emitted by the plant, in grams per thousand BTU it produces.
+	pub fn carbon_intensity(&self) -> u32 {
+		per_useful_kbtu(self.fuel.grams_per_kbtu(), self.efficiency)
+	}
+}
+
+/// Scale a figure per thousand BTU of fuel to one per thousand BTU produced at `efficiency`. A plant
+/// that produces nothing is infinitely expensive.
+fn per_useful_kbtu(per_kbtu: u32, efficiency: u8) -> u32 {
+	match efficiency.min(100) {
+		0 => u32::MAX,
+		efficiency => (per_kbtu as u64 * 100 / efficiency as u64) as u32,
+	}
+}
+
+/// Decides which plants of a [`PowerGrid`] to run, and in which order.
+pub trait DispatchStrategy {
+	/// The indices of the plants to run to meet `demand`, in order. Plants left out are not run,
+	/// plants listed more than once only run the first time, and indices that do not refer to a
+	/// plant are ignored.
+	fn order(&mut self, plants: &[PlantInfo<'_>], demand: BTU) -> Vec<usize>;
+}
+
+/// Runs the plants in the order they were added to the grid. This is the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MeritOrder;
+
+impl DispatchStrategy for MeritOrder {
+	fn order(&mut self, plants: &[PlantInfo<'_>], _demand: BTU) -> Vec<usize> {
+		(0..plants.len()).collect()
+	}
+}
+
+/// Runs the plants with the lowest [`PlantInfo::marginal_cost`] first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LeastCost;
+
+impl DispatchStrategy for LeastCost {
+	fn order(&mut self, plants: &[PlantInfo<'_>], _demand: BTU) -> Vec<usize> {
+		let mut order = (0..plants.len()).collect::<Vec<_>>();
+		order.sort_by_key(|&i| plants[i].marginal_cost());
+		order
+	}
+}
+
+/// Runs the plants with the lowest [`PlantInfo::carbon_intensity`] first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LeastCarbon;
+
+impl DispatchStrategy for LeastCarbon {
+	fn order(&mut self, plants: &[PlantInfo<'_>], _demand: BTU) -> Vec<usize> {
+		let mut order = (0..plants.len()).collect::<Vec<_>>();
+		order.sort_by_key(|&i| plants[i].carbon_intensity());
+		order
+	}
+}
+
+/// Runs the plants in the order they were added, but starting one plant further at every
+/// dispatch, to spread the wear across the fleet.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RoundRobin {
+	next: usize,
+}
+
+impl DispatchStrategy for RoundRobin {
+	fn order(&mut self, plants: &[PlantInfo<'_>], _demand: BTU) -> Vec<usize> {
+		if plants.is_empty() {
+			return Vec::new();
+		}
+		let start = self.next % plants.len();
+		self.next = start + 1;
+		(start..plants.len()).chain(0..start).collect()
+	}
+}
+
+/// A fleet of plants burning fuel from shared inventories.
+///
+/// ```
+/// use pba_qualifier_exam::grid::*;
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let mut grid = PowerGrid::new()
+///     .with_fuel(DynamicFuel::of::<Diesel>(), 50)
+///     .with_plant("diesel", RuntimeGenerator::new(50), DynamicFuel::of::<Diesel>(), 10);
+///
+/// let report = grid.dispatch(800);
+/// assert_eq!(report.supplied(), 500);
+/// assert_eq!(report.shortfall, 300);
+/// assert_eq!(grid.inventory("Diesel"), 40);
+/// # }
+/// ```
+pub struct PowerGrid {
+	stocks: Vec<Stock>,
+	plants: Vec<Plant>,
+	storage: Option<Storage>,
+	strategy: Box<dyn DispatchStrategy>,
+	log: Option<EventLog>,
+	cap: Option<EmissionsCap>,
+	/// The CO2 emitted so far in the current period of the cap, in grams.
+	period_emissions: u64,
+	dispatches: u64,
+	topology: Option<Topology>,
+}
+
+impl Default for PowerGrid {
+	fn default() -> Self {
+		Self {
+			stocks: Vec::new(),
+			plants: Vec::new(),
+			storage: None,
+			strategy: Box::new(MeritOrder),
+			log: None,
+			cap: None,
+			period_emissions: 0,
+			dispatches: 0,
+			topology: None,
+		}
+	}
+}
+
+impl PowerGrid {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add `amount` units of `fuel` to the inventories, up to `u32::MAX` units. Fuels are told
+	/// apart by their name.
+	pub fn stock(&mut self, fuel: DynamicFuel, amount: u32) {
+		let index = self.stock_index(fuel);
+		self.stocks[index].inventory.add(amount);
+	}
+
+	/// Same as [`PowerGrid::stock`], for building up a grid.
+	pub fn with_fuel(mut self, fuel: DynamicFuel, amount: u32) -> Self {
+		self.stock(fuel, amount);
+		self
+	}
+
+	/// Deliver `amount` units of `fuel` to the inventories at `tick`, i.e. right before the
+	/// dispatch of that tick, counting dispatches from zero. See [`Inventory::schedule`].
+	pub fn schedule_delivery(&mut self, fuel: DynamicFuel, tick: u64, amount: u32) {
+		let index = self.stock_index(fuel);
+		self.stocks[index].inventory.schedule(tick, amount);
+	}
+
+	/// Stock the fuel of `inventory`, and schedule its deliveries. The fuel is stocked as
+	/// [`DynamicFuel::of`] `F`, unless a fuel of that name is stocked already.
+	pub fn with_inventory<F: Fuel>(mut self, inventory: Inventory<F>) -> Self {
+		let fuel = DynamicFuel::of::<F>();
+		self.stock(fuel.clone(), inventory.amount());
+		for (tick, amount) in inventory.deliveries {
+			self.schedule_delivery(fuel.clone(), tick, amount);
+		}
+		self
+	}
+
+	/// Add a plant burning at most `max_fuel` units of `fuel` per dispatch. By default, plants are
+	/// dispatched in the order they were added, see [`PowerGrid::with_strategy`].
+	pub fn with_plant(
+		mut self,
+		name: impl Into<String>,
+		provider: impl DynProvideEnergy + 'static,
+		fuel: DynamicFuel,
+		max_fuel: u32,
+	) -> Self {
+		let stock = self.stock_index(fuel);
+		self.plants.push(Plant {
+			name: name.into(),
+			provider: Box::new(provider),
+			stock,
+			max_fuel,
+		});
+		self
+	}
+
+	/// Decide which plants to dispatch, and in which order, with `strategy`.
+	pub fn with_strategy(mut self, strategy: impl DispatchStrategy + 'static) -> Self {
+		self.strategy = Box::new(strategy);
+		self
+	}
+
+	/// Use `storage` to absorb the surplus of each dispatch, and to cover its shortfall.
+	pub fn with_storage(mut self, storage: Storage) -> Self {
+		self.storage = Some(storage);
+		self
+	}
+
+	pub fn storage(&self) -> Option<&Storage> {
+		self.storage.as_ref()
+	}
+
+	/// Record every provision of the plants into an [`EventLog`].
+	pub fn with_event_log(mut self) -> Self {
+		self.log = Some(EventLog::new());
+		self
+	}
+
+	pub fn event_log(&self) -> Option<&EventLog> {
+		self.log.as_ref()
+	}
+
+	/// Transmit the output of the plants to the demand through the lines of `topology`.
+	pub fn with_topology(mut self, topology: Topology) -> Self {
+		self.topology = Some(topology);
+		self
+	}
+
+	/// Stop running plants that emit CO2 once `cap` is reached.
+	pub fn with_emissions_cap(mut self, cap: EmissionsCap) -> Self {
+		self.cap = Some(cap);
+		self
+	}
+
+	/// The CO2 emitted so far in the current period of the [`EmissionsCap`], in grams.
+	pub fn period_emissions(&self) -> u64 {
+		self.period_emissions
+	}
+
+	/// The amount of the fuel named `fuel` left in the inventories.
+	pub fn inventory(&self, fuel: &str) -> u32 {
+		self.stocks
+			.iter()
+			.find(|s| s.fuel.name() == fuel)
+			.map_or(0, |s| s.inventory.amount())
+	}
+
+	/// Run the plants, in the order chosen by the [`DispatchStrategy`] of the grid, until `demand`
+	/// is met or all of them are exhausted.
+	///
+	/// Each plant burns just enough fuel to cover what is left of the demand, as if it were
+	/// perfectly efficient, and burns more if it turns out not to be, within its limit and the
+	/// inventories. Since fuel is burned in whole units, the demand may be slightly exceeded. The
+	/// energy figures of the report saturate at `BTU::MAX`.
+	///
+	/// Whatever the plants could not cover is then discharged from the storage, if any, and
+	/// whatever they produced beyond the demand is charged into it.
+	///
+	/// With a [`Topology`], the output of each plant reaches the demand through the lines of its
+	/// route, losing some of it on the way, and plants that cannot reach the demand are not run.
+	///
+	/// Once the [`EmissionsCap`] of the grid is reached, if any, the plants that emit CO2 are not
+	/// run anymore until the end of the period.
+	///
+	/// The deliveries scheduled up to this dispatch are received first. Plants that should have
+	/// burned more fuel than was left are reported as [`StockOut`]s.
+	///
+	/// Plants that refuse a provision, see [`ProvideEnergy::try_provide_energy`], lose the fuel of
+	/// that provision, are not run any further in this dispatch, and are reported as
+	/// [`PlantFailure`]s.
+	pub fn dispatch(&mut self, demand: BTU) -> DispatchReport {
+		let mut supplied: BTU = 0;
+		let mut plants = Vec::with_capacity(self.plants.len());
+		let mut capped = false;
+		let mut stock_outs = Vec::new();
+		let mut failures = Vec::new();
+
+		for stock in &mut self.stocks {
+			stock.inventory.receive(self.dispatches);
+		}
+
+		if let Some(cap) = self.cap {
+			if cap.period > 0 && self.dispatches % cap.period == 0 {
+				self.period_emissions = 0;
+			}
+		}
+		self.dispatches += 1;
+
+		let info = self
+			.plants
+			.iter()
+			.map(|plant| {
+				let stock = &self.stocks[plant.stock];
+				PlantInfo {
+					name: &plant.name,
+					fuel: &stock.fuel,
+					available_fuel: stock.inventory.amount(),
+					max_fuel: plant.max_fuel,
+					efficiency: plant.provider.efficiency(),
+				}
+			})
+			.collect::<Vec<_>>();
+		let mut order = self.strategy.order(&info, demand);
+		let routes = match &self.topology {
+			Some(topology) => topology.routes(&self.plants),
+			None => vec![Some(Vec::new()); self.plants.len()],
+		};
+		let mut flows = match &self.topology {
+			Some(topology) => topology.idle_flows(),
+			None => Vec::new(),
+		};
+		let mut listed = vec![false; self.plants.len()];
+		order.retain(|&index| {
+			listed
+				.get_mut(index)
+				.map_or(false, |listed| !std::mem::replace(listed, true))
+		});
+		let to_run = order.len();
+		order.extend((0..self.plants.len()).filter(|&index| !listed[index]));
+
+		for (position, index) in order.into_iter().enumerate() {
+			let plant = &self.plants[index];
+			let stock = &mut self.stocks[plant.stock];
+			let density = stock.fuel.density();
+			let (mut fuel_burned, mut output, mut delivered): (u32, BTU, BTU) = (0, 0, 0);
+			let route = routes[index].as_deref();
+
+			let emitting = stock.fuel.grams_per_kbtu() > 0;
+			let over_cap = self
+				.cap
+				.map_or(false, |cap| self.period_emissions >= cap.grams);
+			if position < to_run && emitting && over_cap {
+				capped = true;
+			}
+
+			while position < to_run
+				&& !(emitting && over_cap)
+				&& route.is_some()
+				&& supplied.saturating_add(delivered) < demand
+				&& fuel_burned < plant.max_fuel
+				&& density > 0
+			{
+				let missing = (demand - supplied - delivered) as u64;
+				let needed = ((missing + density as u64 - 1) / density as u64) as u32;
+				let amount = needed
+					.min(plant.max_fuel - fuel_burned)
+					.min(stock.inventory.amount());
+				if amount == 0 {
+					break;
+				}
+				let Some(fuel) = stock.inventory.try_take(amount) else {
+					break;
+				};
+				fuel_burned += fuel.amount();
+				let efficiency = plant.provider.efficiency();
+				let produced = match plant.provider.try_provide_btu(fuel.amount(), density) {
+					Ok(produced) => produced,
+					Err(error) => {
+						failures.push(PlantFailure {
+							plant: plant.name.clone(),
+							error,
+						});
+						0
+					}
+				};
+				output = output.saturating_add(produced);
+				delivered = delivered.saturating_add(match &self.topology {
+					Some(topology) => {
+						topology.transmit(produced, route.unwrap_or_default(), &mut flows)
+					}
+					None => produced,
+				});
+				if let Some(log) = &mut self.log {
+					log.record(&plant.name, stock.fuel.name(), amount, efficiency, produced);
+				}
+				if produced == 0 {
+					break;
+				}
+			}
+
+			let wanted = position < to_run
+				&& !(emitting && over_cap)
+				&& route.is_some()
+				&& supplied.saturating_add(delivered) < demand
+				&& fuel_burned < plant.max_fuel
+				&& density > 0;
+			if wanted && stock.inventory.amount() == 0 {
+				stock_outs.push(StockOut {
+					plant: plant.name.clone(),
+					fuel: stock.fuel.name().to_string(),
+				});
+			}
+
+			supplied = supplied.saturating_add(delivered);
+			let released = fuel_burned as u64 * density as u64;
+			let emissions = released.saturating_mul(stock.fuel.grams_per_kbtu() as u64) / 1000;
+			self.period_emissions = self.period_emissions.saturating_add(emissions);
+			plants.push(PlantDispatch {
+				plant: plant.name.clone(),
+				fuel_burned,
+				output,
+				emissions,
+			});
+		}
+
+		let (mut shortfall, mut surplus) = (
+			demand.saturating_sub(supplied),
+			supplied.saturating_sub(demand),
+		);
+		let (mut discharged, mut charged) = (0, 0);
+		if let Some(storage) = &mut self.storage {
+			discharged = storage.discharge(shortfall);
+			charged = storage.charge(surplus);
+			shortfall -= discharged;
+			surplus -= charged;
+		}
+
+		DispatchReport {
+			demand,
+			plants,
+			discharged,
+			charged,
+			shortfall,
+			surplus,
+			capped,
+			flows,
+			stock_outs,
+			failures,
+		}
+	}
+
+	/// The index of the stock of `fuel`, adding an empty one if there is none yet.
+	fn stock_index(&mut self, fuel: DynamicFuel) -> usize {
+		match self
+			.stocks
+			.iter()
+			.position(|s| s.fuel.name() == fuel.name())
+		{
+			Some(index) => index,
+			None => {
+				self.stocks.push(Stock {
+					fuel,
+					inventory: Inventory::new(0),
+				});
+				self.stocks.len() - 1
+			}
+		}
+	}
+}
+
+/// The buses of a [`PowerGrid`], and the lines connecting them.
+///
+/// All of the demand sits at a single load bus, and plants sit at the load bus unless placed
+/// elsewhere. The output of a plant is sent over the route that loses the least of it.
+///
+/// ```
+/// use pba_qualifier_exam::grid::*;
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let topology = Topology::new("city")
+///     .with_line("dam", "city", Percent::new(10))
+///     .with_plant_at("hydro", "dam");
+/// let water = DynamicFuel::new("water", 100);
+/// let mut grid = PowerGrid::new()
+///     .with_fuel(water.clone(), 10)
+///     .with_plant("hydro", RuntimeGenerator::new(100), water, 10)
+///     .with_topology(topology);
+///
+/// let report = grid.dispatch(180);
+/// assert_eq!((report.supplied(), report.losses(), report.delivered()), (200, 20, 180));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Topology {
+	buses: Vec<String>,
+	lines: Vec<Line>,
+	/// The buses of the plants placed so far, by plant name.
+	plants: Vec<(String, usize)>,
+}
+
+/// A transmission line between two buses of a [`Topology`], usable both ways.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Line {
+	from: usize,
+	to: usize,
+	loss: Percent,
+}
+
+/// One line of a route, crossed from its `from` bus to its `to` bus if `forward`.
+#[derive(Clone, Copy, Debug)]
+struct Hop {
+	line: usize,
+	forward: bool,
+}
+
+impl Topology {
+	/// A topology with only the load bus, named `load`.
+	pub fn new(load: impl Into<String>) -> Self {
+		Self {
+			buses: vec![load.into()],
+			lines: Vec::new(),
+			plants: Vec::new(),
+		}
+	}
+
+	/// Connect the buses `from` and `to`, adding them if needed, with a line losing `loss` of the
+	/// energy sent through it.
+	pub fn with_line(mut self, from: &str, to: &str, loss: Percent) -> Self {
+		let (from, to) = (self.bus(from), self.bus(to));
+		self.lines.push(Line { from, to, loss });
+		self
+	}
+
+	/// Place the plant named `plant` at `bus`, adding the bus if needed.
+	pub fn with_plant_at(mut self, plant: impl Into<String>, bus: &str) -> Self {
+		let bus = self.bus(bus);
+		self.plants.push((plant.into(), bus));
+		self
+	}
+
+	fn bus(&mut self, name: &str) -> usize {
+		match self.buses.iter().position(|b| b == name) {
+			Some(index) => index,
+			None => {
+				self.buses.push(name.to_string());
+				self.buses.len() - 1
+			}
+		}
+	}
+
+	/// The route of each plant to the load bus, or `None` for the plants that cannot reach it.
+	fn routes(&self, plants: &[Plant]) -> Vec<Option<Vec<Hop>>> {
+		// The next hop of the least lossy route of every bus, found with Bellman-Ford. A route
+		// keeps the product of the kept fractions of its lines.
+		let mut kept = vec![0.0; self.buses.len()];
+		let mut next = vec![None; self.buses.len()];
+		kept[0] = 1.0;
+		for _ in 0..self.buses.len() {
+			for (index, line) in self.lines.iter().enumerate() {
+				let fraction = 1.0 - line.loss.get() as f64 / 100.0;
+				for (start, end, forward) in
+					[(line.from, line.to, true), (line.to, line.from, false)]
+				{
+					if kept[end] * fraction > kept[start] {
+						kept[start] = kept[end] * fraction;
+						next[start] = Some(Hop {
+							line: index,
+							forward,
+						});
+					}
+				}
+			}
+		}
+
+		plants
+			.iter()
+			.map(|plant| {
+				let mut bus = self
+					.plants
+					.iter()
+					.find(|(name, _)| *name == plant.name)
+					.map_or(0, |(_, bus)| *bus);
+				let mut route = Vec::new();
+				while bus != 0 {
+					let hop = next[bus]?;
+					let line = &self.lines[hop.line];
+					bus = if hop.forward { line.to } else { line.from };
+					route.push(hop);
+				}
+				Some(route)
+			})
+			.collect()
+	}
+
+	/// Send `energy` over `route`, recording what flows through each line, and return what is left
+	/// at its end.
+	fn transmit(&self, mut energy: BTU, route: &[Hop], flows: &mut [LineFlow]) -> BTU {
+		for hop in route {
+			let line = &self.lines[hop.line];
+			let flow = &mut flows[hop.line];
+			// All routes lead to the load bus, so each line is only ever crossed one way.
+			if !hop.forward && flow.from == self.buses[line.from] {
+				std::mem::swap(&mut flow.from, &mut flow.to);
+			}
+			let loss = line.loss.of(energy);
+			flow.flow = flow.flow.saturating_add(energy);
+			flow.loss = flow.loss.saturating_add(loss);
+			energy -= loss;
+		}
+		energy
+	}
+
+	/// Every line, with nothing flowing through it yet.
+	fn idle_flows(&self) -> Vec<LineFlow> {
+		self.lines
+			.iter()
+			.map(|line| LineFlow {
+				from: self.buses[line.from].clone(),
+				to: self.buses[line.to].clone(),
+				flow: 0,
+				loss: 0,
+			})
+			.collect()
+	}
+}
+
+/// What flowed through a line of a [`Topology`] in a dispatch, from `from` to `to`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineFlow {
+	pub from: String,
+	pub to: String,
+	/// The energy sent into the line.
+	pub flow: BTU,
+	/// The part of the flow lost on the way.
+	pub loss: BTU,
+}
+
+/// A limit on the CO2 a [`PowerGrid`] may emit per period of `period` dispatches, e.g. ticks of a
+/// [`Simulation`]. A period of zero never ends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EmissionsCap {
+	/// The limit, in grams of CO2.
+	pub grams: u64,
+	pub period: u64,
+}
+
+/// What a single plant did in a [`PowerGrid::dispatch`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlantDispatch {
+	pub plant: String,
+	pub fuel_burned: u32,
+	pub output: BTU,
+	/// The CO2 emitted by burning the fuel, in grams.
+	pub emissions: u64,
+}
+
+/// The outcome of a [`PowerGrid::dispatch`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DispatchReport {
+	pub demand: BTU,
+	/// Every plant of the grid, in dispatch order, followed by the plants that were not run.
+	pub plants: Vec<PlantDispatch>,
+	/// The energy taken out of the storage to cover a shortfall of the plants.
+	pub discharged: BTU,
+	/// The energy put into the storage out of a surplus of the plants.
+	pub charged: BTU,
+	/// The part of the demand that could not be met.
+	pub shortfall: BTU,
+	/// The energy produced beyond the demand, and not stored.
+	pub surplus: BTU,
+	/// Whether some plants were not run because the [`EmissionsCap`] was reached.
+	pub capped: bool,
+	/// The flow through every line of the [`Topology`] of the grid, if any.
+	pub flows: Vec<LineFlow>,
+	/// The plants that ran out of fuel, in dispatch order.
+	pub stock_outs: Vec<StockOut>,
+	/// The plants that refused to provide energy, in dispatch order.
+	pub failures: Vec<PlantFailure>,
+}
+
+/// A plant of a [`PowerGrid`] that could not be fueled, since the inventory of its fuel was empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StockOut {
+	pub plant: String,
+	pub fuel: String,
+}
+
+/// A plant of a [`PowerGrid`] that refused to provide energy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlantFailure {
+	pub plant: String,
+	pub error: ProvisionError,
+}
+
+impl DispatchReport {
+	/// The total output of all plants, including what was lost in transmission, up to `BTU::MAX`.
+	pub fn supplied(&self) -> BTU {
+		self.plants
+			.iter()
+			.fold(0, |total: BTU, p| total.saturating_add(p.output))
+	}
+
+	/// The energy lost in transmission, up to `BTU::MAX`.
+	pub fn losses(&self) -> BTU {
+		self.flows
+			.iter()
+			.fold(0, |total: BTU, f| total.saturating_add(f.loss))
+	}
+
+	/// The output of the plants that reached the demand.
+	pub fn delivered(&self) -> BTU {
+		self.supplied().saturating_sub(self.losses())
+	}
+
+	/// The CO2 emitted by all plants, in grams.
+	pub fn emissions(&self) -> u64 {
+		self.plants.iter().map(|p| p.emissions).sum()
+	}
+}
+
+impl ToCsv for DispatchReport {
+	/// One line per plant.
+	fn to_csv(&self, mut out: impl std::io::Write) -> std::io::Result<()> {
+		writeln!(out, "plant,fuel_burned,output,emissions")?;
+		for plant in &self.plants {
+			writeln!(
+				out,
+				"{},{},{},{}",
+				escape(&plant.plant),
+				plant.fuel_burned,
+				plant.output,
+				plant.emissions
+			)?;
+		}
+		out.flush()
+	}
+}
+
+/// A single provision of energy, as recorded in an [`EventLog`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProvisionEvent {
+	pub tick: u64,
+	/// The name of the plant or provider.
+	pub provider: String,
+	pub fuel: String,
+	/// The amount of fuel burned.
+	pub amount: u32,
+	/// The efficiency the provider applied, in percent.
+	pub efficiency: u8,
+	pub output: BTU,
+}
+
+/// Every provision made by a [`PowerGrid`] or by [`Logged`] providers, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventLog {
+	events: Vec<ProvisionEvent>,
+	tick: u64,
+}
+
+impl EventLog {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Record the coming provisions as happening at `tick`. A [`Simulation`] does so for the event
+	/// log of its grid.
+	pub fn set_tick(&mut self, tick: u64) {
+		self.tick = tick;
+	}
+
+	/// Record a provision at the current tick.
+	pub fn record(&mut self, provider: &str, fuel: &str, amount: u32, efficiency: u8, output: BTU) {
+		self.events.push(ProvisionEvent {
+			tick: self.tick,
+			provider: provider.to_string(),
+			fuel: fuel.to_string(),
+			amount,
+			efficiency,
+			output,
+		});
+	}
+
+	pub fn events(&self) -> &[ProvisionEvent] {
+		&self.events
+	}
+
+	/// The provisions made by `provider`, in order.
+	pub fn events_for_provider<'a>(
+		&'a self,
+		provider: &'a str,
+	) -> impl Iterator<Item = &'a ProvisionEvent> + 'a {
+		self.events.iter().filter(move |e| e.provider == provider)
+	}
+
+	/// The total output per fuel.
+	pub fn total_by_fuel(&self) -> BTreeMap<&str, u64> {
+		let mut totals = BTreeMap::new();
+		for event in &self.events {
+			*totals.entry(event.fuel.as_str()).or_insert(0) += event.output as u64;
+		}
+		totals
+	}
+}
+
+/// Wraps a provider and records each of its provisions, under the name `id`, into an [`EventLog`]
+/// that may be shared with other providers.
+pub struct Logged<P> {
+	provider: P,
+	id: String,
+	log: Rc<RefCell<EventLog>>,
+}
+
+impl<P> Logged<P> {
+	pub fn new(provider: P, id: impl Into<String>, log: Rc<RefCell<EventLog>>) -> Self {
+		Self {
+			provider,
+			id: id.into(),
+			log,
+		}
+	}
+}
+
+impl<F: Fuel, P: ProvideEnergy<F>> ProvideEnergy<F> for Logged<P> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		let amount = f.amount();
+		let efficiency = self.provider.current_efficiency();
+		let output = self.provider.try_provide_energy(f).map(Into::<BTU>::into);
+		self.log.borrow_mut().record(
+			&self.id,
+			F::name(),
+			amount,
+			efficiency,
+			*output.as_ref().unwrap_or(&0),
+		);
+		output.map(Into::into)
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+/// Something that draws energy from a [`PowerGrid`], e.g. a city.
+pub trait Consumer {
+	/// The energy needed at `tick`.
+	fn demand_at(&self, tick: u64) -> BTU;
+}
+
+impl<C: Fn(u64) -> BTU> Consumer for C {
+	fn demand_at(&self, tick: u64) -> BTU {
+		self(tick)
+	}
+}
+
+/// The same demand at every tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Constant(pub BTU);
+
+impl Consumer for Constant {
+	fn demand_at(&self, _tick: u64) -> BTU {
+		self.0
+	}
+}
+
+/// A demand following a day and night cycle of `period` ticks: `base` on average, peaking at
+/// `base + amplitude` a quarter into the cycle, and bottoming out at `base - amplitude` (or zero)
+/// three quarters into it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Diurnal {
+	pub base: BTU,
+	pub amplitude: BTU,
+	pub period: u64,
+}
+
+impl Consumer for Diurnal {
+	fn demand_at(&self, tick: u64) -> BTU {
+		if self.period == 0 {
+			return self.base;
+		}
+		let phase = (tick % self.period) as f64 / self.period as f64;
+		let swing = self.amplitude as f64 * (2.0 * std::f64::consts::PI * phase).sin();
+		(self.base as f64 + swing).round().max(0.0) as BTU
+	}
+}
+
+/// A demand of `before` until tick `at`, and of `after` from then on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+	pub before: BTU,
+	pub after: BTU,
+	pub at: u64,
+}
+
+impl Consumer for Step {
+	fn demand_at(&self, tick: u64) -> BTU {
+		if tick < self.at {
+			self.before
+		} else {
+			self.after
+		}
+	}
+}
+
+/// What happened at a single tick of a [`Simulation`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TickResult {
+	pub tick: u64,
+	pub report: DispatchReport,
+}
+
+impl ToCsv for [TickResult] {
+	/// One line per tick, with the totals of its [`DispatchReport`].
+	fn to_csv(&self, mut out: impl std::io::Write) -> std::io::Result<()> {
+		writeln!(
+			out,
+			"tick,demand,supplied,discharged,charged,shortfall,surplus,emissions"
+		)?;
+		for tick in self {
+			let report = &tick.report;
+			writeln!(
+				out,
+				"{},{},{},{},{},{},{},{}",
+				tick.tick,
+				report.demand,
+				report.supplied(),
+				report.discharged,
+				report.charged,
+				report.shortfall,
+				report.surplus,
+				report.emissions()
+			)?;
+		}
+		out.flush()
+	}
+}
+
+/// Totals over the history of a [`Simulation`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimulationSummary {
+	pub ticks: u64,
+	pub demand: u64,
+	/// The energy produced by the plants.
+	pub supplied: u64,
+	/// The demand that could not be met, even with the storage.
+	pub unserved: u64,
+	/// The CO2 emitted, in grams.
+	pub emissions: u64,
+	/// The number of times a plant ran out of fuel.
+	pub stock_outs: u64,
+}
+
+/// Drives a [`PowerGrid`] through time: at every tick, the demand of all consumers is added up and
+/// dispatched. The total demand of a tick saturates at `BTU::MAX`.
+///
+/// ```
+/// use pba_qualifier_exam::grid::*;
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let grid = PowerGrid::new()
+///     .with_fuel(DynamicFuel::of::<Diesel>(), 6)
+///     .with_plant("diesel", RuntimeGenerator::new(100), DynamicFuel::of::<Diesel>(), 5);
+/// let mut simulation = Simulation::new(grid)
+///     .with_consumer(Constant(100))
+///     .with_consumer(Step { before: 0, after: 200, at: 2 });
+///
+/// simulation.run(4);
+/// let shortfalls = simulation
+///     .history()
+///     .iter()
+///     .map(|t| t.report.shortfall)
+///     .collect::<Vec<_>>();
+/// assert_eq!(shortfalls, [0, 0, 0, 200]);
+/// # }
+/// ```
+pub struct Simulation {
+	grid: PowerGrid,
+	consumers: Vec<Box<dyn Consumer>>,
+	tick: u64,
+	history: Vec<TickResult>,
+	/// The scenario the simulation was built from, if any.
+	#[cfg(feature = "snapshot")]
+	scenario: Option<crate::scenario::Scenario>,
+}
+
+impl Simulation {
+	pub fn new(grid: PowerGrid) -> Self {
+		Self {
+			grid,
+			consumers: Vec::new(),
+			tick: 0,
+			history: Vec::new(),
+			#[cfg(feature = "snapshot")]
+			scenario: None,
+		}
+	}
+
+	pub fn with_consumer(mut self, consumer: impl Consumer + 'static) -> Self {
+		self.consumers.push(Box::new(consumer));
+		self
+	}
+
+	/// The next tick to be simulated.
+	pub fn tick(&self) -> u64 {
+		self.tick
+	}
+
+	pub fn grid(&self) -> &PowerGrid {
+		&self.grid
+	}
+
+	/// The grid, e.g. to restock its inventories in between ticks.
+	pub fn grid_mut(&mut self) -> &mut PowerGrid {
+		&mut self.grid
+	}
+
+	/// All ticks simulated so far, in order.
+	pub fn history(&self) -> &[TickResult] {
+		&self.history
+	}
+
+	/// The totals of all ticks simulated so far.
+	pub fn summary(&self) -> SimulationSummary {
+		self.history
+			.iter()
+			.fold(SimulationSummary::default(), |summary, tick| {
+				SimulationSummary {
+					ticks: summary.ticks + 1,
+					demand: summary.demand + tick.report.demand as u64,
+					supplied: summary.supplied + tick.report.supplied() as u64,
+					unserved: summary.unserved + tick.report.shortfall as u64,
+					emissions: summary.emissions + tick.report.emissions(),
+					stock_outs: summary.stock_outs + tick.report.stock_outs.len() as u64,
+				}
+			})
+	}
+
+	/// Every [`StockOut`] so far, along with its tick.
+	pub fn stock_outs(&self) -> impl Iterator<Item = (u64, &StockOut)> + '_ {
+		self.history
+			.iter()
+			.flat_map(|t| t.report.stock_outs.iter().map(move |s| (t.tick, s)))
+	}
+
+	/// Simulate the next tick.
+	pub fn step(&mut self) -> &TickResult {
+		let demand = self.consumers.iter().fold(0, |total: BTU, c| {
+			total.saturating_add(c.demand_at(self.tick))
+		});
+		if let Some(log) = &mut self.grid.log {
+			log.set_tick(self.tick);
+		}
+		let report = self.grid.dispatch(demand);
+		self.history.push(TickResult {
+			tick: self.tick,
+			report,
+		});
+		self.tick += 1;
+		self.history.last().expect("a tick was just pushed")
+	}
+
+	/// Simulate the next `ticks` ticks, and return their results.
+	pub fn run(&mut self, ticks: u64) -> &[TickResult] {
+		let start = self.history.len();
+		for _ in 0..ticks {
+			self.step();
+		}
+		&self.history[start..]
+	}
+}
+
+/// A small pseudo-random generator (splitmix64), such that stochastic simulations are reproducible
+/// from their seed, across runs and platforms.
+#[cfg(feature = "stochastic")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeededRng(u64);
+
+#[cfg(feature = "stochastic")]
+impl SeededRng {
+	pub fn new(seed: u64) -> Self {
+		Self(seed)
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z ^ (z >> 31)
+	}
+
+	/// A number in `0..bound`. Panics if `bound` is zero.
+	pub fn next_below(&mut self, bound: u64) -> u64 {
+		assert!(bound > 0, "bound must not be zero");
+		self.next_u64() % bound
+	}
+
+	/// True `percent` times out of 100, on average.
+	pub fn chance(&mut self, percent: u8) -> bool {
+		self.next_below(100) < percent as u64
+	}
+}
+
+/// Wraps a consumer and adds up to `percent` percent of noise to its demand, in either direction.
+///
+/// The noise of a tick only depends on the seed and the tick, so it is the same however many times
+/// and in whichever order the ticks are asked for.
+#[cfg(feature = "stochastic")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Noisy<C> {
+	pub consumer: C,
+	pub seed: u64,
+	pub percent: u8,
+}
+
+#[cfg(feature = "stochastic")]
+impl<C: Consumer> Consumer for Noisy<C> {
+	fn demand_at(&self, tick: u64) -> BTU {
+		let percent = self.percent.min(100) as i64;
+		let mut rng = SeededRng::new(self.seed ^ tick.wrapping_mul(0xD6E8_FEB8_6659_FD93));
+		let noise = rng.next_below(2 * percent as u64 + 1) as i64 - percent;
+		let demand = self.consumer.demand_at(tick) as i64;
+		(demand * (100 + noise) / 100) as BTU
+	}
+}
+
+/// Wraps a [`DispatchStrategy`] and takes each plant out of a dispatch, as if it suffered an
+/// outage, `percent` times out of 100 on average. Outages follow a pseudo-random pattern
+/// derived from `seed`.
+#[cfg(feature = "stochastic")]
+pub struct WithOutages<S> {
+	strategy: S,
+	rng: SeededRng,
+	percent: u8,
+}
+
+#[cfg(feature = "stochastic")]
+impl<S> WithOutages<S> {
+	pub fn new(strategy: S, seed: u64, percent: u8) -> Self {
+		Self {
+			strategy,
+			rng: SeededRng::new(seed),
+			percent: percent.min(100),
+		}
+	}
+}
+
+#[cfg(feature = "stochastic")]
+impl<S: DispatchStrategy> DispatchStrategy for WithOutages<S> {
+	fn order(&mut self, plants: &[PlantInfo<'_>], demand: BTU) -> Vec<usize> {
+		let out = (0..plants.len())
+			.map(|_| self.rng.chance(self.percent))
+			.collect::<Vec<_>>();
+		let mut order = self.strategy.order(plants, demand);
+		order.retain(|&index| out.get(index) == Some(&false));
+		order
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::h_advanced_traits::{
+		Coal, Cooldown, Diesel, NuclearReactor, RuntimeGenerator, WearByFuel,
+	};
+
+	#[test]
+	fn dispatch_in_priority_order() {
+		let uranium = DynamicFuel::new("uranium", 500);
+		let gas = DynamicFuel::new("gas", 100);
+		let mut grid = PowerGrid::new()
+			.with_fuel(uranium.clone(), 5)
+			.with_fuel(gas.clone(), 100)
+			.with_plant("nuclear", NuclearReactor, uranium, 1)
+			.with_plant("peaker", RuntimeGenerator::new(50), gas, 10);
+
+		let report = grid.dispatch(1000);
+		let dispatch = |plant: &str, fuel_burned, output| PlantDispatch {
+			plant: plant.to_string(),
+			fuel_burned,
+			output,
+			emissions: 0,
+		};
+		assert_eq!(
+			report.plants,
+			vec![dispatch("nuclear", 1, 495), dispatch("peaker", 10, 500)]
+		);
+		assert_eq!(
+			(report.supplied(), report.shortfall, report.surplus),
+			(995, 5, 0)
+		);
+		assert_eq!((grid.inventory("uranium"), grid.inventory("gas")), (4, 90));
+		assert_eq!(grid.inventory("coal"), 0);
+	}
+
+	#[test]
+	fn dispatch_surplus_and_stock_outs() {
+		let diesel = DynamicFuel::of::<Diesel>();
+		let mut grid = PowerGrid::new().with_fuel(diesel.clone(), 3).with_plant(
+			"diesel",
+			RuntimeGenerator::new(100),
+			diesel,
+			10,
+		);
+
+		let report = grid.dispatch(150);
+		assert_eq!(
+			(report.supplied(), report.shortfall, report.surplus),
+			(200, 0, 50)
+		);
+
+		let report = grid.dispatch(150);
+		assert_eq!(report.plants[0].fuel_burned, 1);
+		assert_eq!(
+			(report.supplied(), report.shortfall, report.surplus),
+			(100, 50, 0)
+		);
+
+		assert_eq!(grid.dispatch(0).plants[0].fuel_burned, 0);
+		assert_eq!(grid.dispatch(10).shortfall, 10);
+	}
+
+	#[test]
+	fn dispatch_saturates_at_max_demand() {
+		let gas = DynamicFuel::new("gas", 1000);
+		let mut grid = PowerGrid::new()
+			.with_fuel(gas.clone(), u32::MAX)
+			.with_fuel(gas.clone(), u32::MAX)
+			.with_plant("base", RuntimeGenerator::new(100), gas.clone(), 3_000_000)
+			.with_plant("peaker", RuntimeGenerator::new(100), gas, u32::MAX);
+		assert_eq!(grid.inventory("gas"), u32::MAX);
+
+		let report = grid.dispatch(BTU::MAX);
+		assert_eq!(report.plants[0].output, 3_000_000_000);
+		assert_eq!(report.plants[1].fuel_burned, 1_294_968);
+		assert_eq!(
+			(report.supplied(), report.shortfall, report.surplus),
+			(BTU::MAX, 0, 0)
+		);
+	}
+
+	#[test]
+	fn dispatch_ignores_unknown_plants() {
+		struct Reckless;
+		impl DispatchStrategy for Reckless {
+			fn order(&mut self, _plants: &[PlantInfo<'_>], _demand: BTU) -> Vec<usize> {
+				vec![7, 1, usize::MAX]
+			}
+		}
+
+		let gas = DynamicFuel::new("gas", 10);
+		let grid = || {
+			PowerGrid::new()
+				.with_fuel(gas.clone(), 100)
+				.with_plant("first", RuntimeGenerator::new(100), gas.clone(), 10)
+				.with_plant("second", RuntimeGenerator::new(100), gas.clone(), 10)
+		};
+
+		let report = grid().with_strategy(Reckless).dispatch(50);
+		let plants = report
+			.plants
+			.iter()
+			.map(|p| p.plant.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(plants, ["second", "first"]);
+		assert_eq!((report.plants[0].output, report.plants[1].output), (50, 0));
+
+		#[cfg(feature = "stochastic")]
+		{
+			let report = grid()
+				.with_strategy(WithOutages::new(Reckless, 1, 0))
+				.dispatch(50);
+			assert_eq!(report.plants[0].output, 50);
+		}
+	}
+
+	#[test]
+	fn dispatch_reports_failed_plants() {
+		let gas = DynamicFuel::new("gas", 100);
+		let peaker = Cooldown::<_, 1>::new(RuntimeGenerator::new(100));
+		let mut grid = PowerGrid::new()
+			.with_fuel(gas.clone(), 100)
+			.with_plant("peaker", peaker, gas.clone(), 1)
+			.with_plant("backup", RuntimeGenerator::new(50), gas, 10);
+
+		let report = grid.dispatch(100);
+		assert!(report.failures.is_empty());
+		assert_eq!(report.plants[1].fuel_burned, 0);
+
+		let report = grid.dispatch(100);
+		let failure = PlantFailure {
+			plant: "peaker".to_string(),
+			error: ProvisionError::CoolingDown,
+		};
+		assert_eq!(report.failures, [failure]);
+		assert_eq!(
+			(report.plants[0].fuel_burned, report.plants[0].output),
+			(1, 0)
+		);
+		assert_eq!(
+			(report.plants[1].fuel_burned, report.plants[1].output),
+			(2, 100)
+		);
+		assert_eq!((report.shortfall, grid.inventory("gas")), (0, 96));
+	}
+
+	#[test]
+	fn storage_capacity_and_losses() {
+		let mut storage = Storage::new(100, Percent::new(80));
+		assert_eq!(storage.charge(50), 50);
+		assert_eq!((storage.stored(), storage.losses()), (40, 10));
+
+		// Only 75 more fit, once the losses are accounted for.
+		assert_eq!(storage.charge(500), 75);
+		assert_eq!((storage.stored(), storage.losses()), (100, 25));
+		assert_eq!(storage.charge(1), 0);
+
+		assert_eq!(storage.discharge(30), 30);
+		assert_eq!(storage.discharge(200), 70);
+		assert_eq!(storage.discharge(1), 0);
+
+		assert_eq!(Storage::new(100, Percent::new(0)).charge(10), 0);
+		assert_eq!(Percent::new(150).get(), 100);
+	}
+
+	#[test]
+	fn storage_covers_shortfalls() {
+		let gas = DynamicFuel::new("gas", 100);
+		let mut grid = PowerGrid::new()
+			.with_fuel(gas.clone(), 3)
+			.with_plant("gas", RuntimeGenerator::new(100), gas, 10)
+			.with_storage(Storage::new(1000, Percent::new(50)));
+
+		let report = grid.dispatch(150);
+		assert_eq!((report.charged, report.surplus), (50, 0));
+		assert_eq!(grid.storage().unwrap().stored(), 25);
+
+		let report = grid.dispatch(150);
+		assert_eq!((report.supplied(), report.discharged), (100, 25));
+		assert_eq!(report.shortfall, 25);
+		assert_eq!(grid.storage().unwrap().stored(), 0);
+	}
+
+	#[test]
+	fn demand_profiles() {
+		let diurnal = Diurnal {
+			base: 100,
+			amplitude: 50,
+			period: 4,
+		};
+		let demand = (0..5).map(|t| diurnal.demand_at(t)).collect::<Vec<_>>();
+		assert_eq!(demand, [100, 150, 100, 50, 100]);
+
+		let step = Step {
+			before: 1,
+			after: 2,
+			at: 3,
+		};
+		assert_eq!((step.demand_at(2), step.demand_at(3)), (1, 2));
+		assert_eq!((|tick: u64| tick as BTU * 10).demand_at(4), 40);
+	}
+
+	#[test]
+	fn simulation_accumulates_ticks() {
+		let gas = DynamicFuel::new("gas", 10);
+		let grid = PowerGrid::new().with_fuel(gas.clone(), 25).with_plant(
+			"gas",
+			RuntimeGenerator::new(100),
+			gas,
+			100,
+		);
+		let mut simulation = Simulation::new(grid)
+			.with_consumer(Constant(50))
+			.with_consumer(|tick| tick as BTU * 10);
+
+		let results = simulation.run(3);
+		let demand = results.iter().map(|t| t.report.demand).collect::<Vec<_>>();
+		assert_eq!(demand, [50, 60, 70]);
+		assert_eq!(results[2].report.shortfall, 0);
+
+		let result = simulation.step();
+		assert_eq!((result.tick, result.report.demand), (3, 80));
+		assert_eq!(result.report.shortfall, 10);
+		assert_eq!(simulation.tick(), 4);
+		assert_eq!(simulation.history().len(), 4);
+
+		simulation.grid_mut().stock(DynamicFuel::new("gas", 10), 9);
+		assert_eq!(simulation.step().report.shortfall, 0);
+	}
+
+	#[test]
+	fn simulation_demand_saturates() {
+		let mut simulation = Simulation::new(PowerGrid::new())
+			.with_consumer(Constant(3_000_000_000))
+			.with_consumer(Constant(3_000_000_000));
+
+		let report = &simulation.step().report;
+		assert_eq!((report.demand, report.shortfall), (BTU::MAX, BTU::MAX));
+	}
+
+	#[test]
+	fn dispatch_strategies() {
+		let coal = DynamicFuel::new("coal", 100)
+			.with_cost(1000)
+			.with_carbon_intensity(95);
+		let gas = DynamicFuel::new("gas", 100)
+			.with_cost(2000)
+			.with_carbon_intensity(53);
+		let uranium = DynamicFuel::new("uranium", 100)
+			.with_cost(500)
+			.with_carbon_intensity(4);
+		let grid = || {
+			PowerGrid::new()
+				.with_fuel(coal.clone(), 100)
+				.with_fuel(gas.clone(), 100)
+				.with_fuel(uranium.clone(), 100)
+				// 5% efficient, hence both the most expensive and more polluting than gas.
+				.with_plant("nuclear", RuntimeGenerator::new(5), uranium.clone(), 1)
+				.with_plant("gas", RuntimeGenerator::new(100), gas.clone(), 1)
+				.with_plant("coal", RuntimeGenerator::new(100), coal.clone(), 1)
+		};
+		let run = |grid: &mut PowerGrid| {
+			grid.dispatch(100)
+				.plants
+				.into_iter()
+				.map(|p| (p.plant, p.output))
+				.collect::<Vec<_>>()
+		};
+		let names = |run: Vec<(String, BTU)>| run.into_iter().map(|(p, _)| p).collect::<Vec<_>>();
+
+		assert_eq!(names(run(&mut grid())), ["nuclear", "gas", "coal"]);
+		assert_eq!(
+			names(run(&mut grid().with_strategy(LeastCost))),
+			["coal", "gas", "nuclear"]
+		);
+		assert_eq!(
+			run(&mut grid().with_strategy(LeastCarbon)),
+			[
+				("gas".to_string(), 100),
+				("nuclear".to_string(), 0),
+				("coal".to_string(), 0),
+			]
+		);
+
+		let mut round_robin = grid().with_strategy(RoundRobin::default());
+		let first_run = (0..4)
+			.map(|_| run(&mut round_robin)[0].0.clone())
+			.collect::<Vec<_>>();
+		assert_eq!(first_run, ["nuclear", "gas", "coal", "nuclear"]);
+
+		// Only runs the gas plant, once.
+		struct GasOnly;
+		impl DispatchStrategy for GasOnly {
+			fn order(&mut self, _: &[PlantInfo<'_>], _: BTU) -> Vec<usize> {
+				vec![1, 1]
+			}
+		}
+		let mut grid = grid().with_strategy(GasOnly);
+		let report = grid.dispatch(300);
+		assert_eq!((report.plants[0].output, report.shortfall), (100, 200));
+		assert_eq!(report.plants.len(), 3);
+	}
+
+	#[test]
+	fn event_log_of_a_simulation() {
+		let gas = DynamicFuel::new("gas", 100);
+		let coal = DynamicFuel::new("coal", 10);
+		let grid = PowerGrid::new()
+			.with_fuel(gas.clone(), 100)
+			.with_fuel(coal.clone(), 100)
+			.with_plant("gas", RuntimeGenerator::new(50), gas, 1)
+			.with_plant("coal", RuntimeGenerator::new(100), coal, 100)
+			.with_event_log();
+		let mut simulation = Simulation::new(grid).with_consumer(Constant(100));
+		simulation.run(2);
+
+		let log = simulation.grid().event_log().unwrap();
+		let gas_events = log.events_for_provider("gas").collect::<Vec<_>>();
+		assert_eq!(gas_events.len(), 2);
+		assert_eq!(
+			*gas_events[1],
+			ProvisionEvent {
+				tick: 1,
+				provider: "gas".to_string(),
+				fuel: "gas".to_string(),
+				amount: 1,
+				efficiency: 50,
+				output: 50,
+			}
+		);
+		assert_eq!(log.events_for_provider("coal").count(), 2);
+		assert_eq!(
+			log.total_by_fuel().into_iter().collect::<Vec<_>>(),
+			[("coal", 100), ("gas", 100)]
+		);
+	}
+
+	#[test]
+	fn logged_providers_share_a_log() {
+		let log = Rc::new(RefCell::new(EventLog::new()));
+		let diesel = Logged::new(RuntimeGenerator::new(50), "diesel", log.clone());
+		let nuclear = Logged::new(NuclearReactor, "nuclear", log.clone());
+
+		diesel.provide_energy(FuelContainer::<Diesel>::new(2));
+		log.borrow_mut().set_tick(1);
+		nuclear.provide_energy(FuelContainer::<Diesel>::new(1));
+
+		let log = log.borrow();
+		let ticks = log.events().iter().map(|e| e.tick).collect::<Vec<_>>();
+		assert_eq!(ticks, [0, 1]);
+		assert_eq!(log.events()[0].output, 100);
+		assert_eq!(log.total_by_fuel()["Diesel"], 199);
+	}
+
+	#[test]
+	fn simulation_summary() {
+		let coal = DynamicFuel::new("coal", 100).with_carbon_intensity(95);
+		let grid = PowerGrid::new().with_fuel(coal.clone(), 5).with_plant(
+			"coal",
+			RuntimeGenerator::new(50),
+			coal,
+			2,
+		);
+		let mut simulation = Simulation::new(grid).with_consumer(Constant(100));
+
+		assert_eq!(simulation.run(1)[0].report.emissions(), 19);
+		simulation.run(3);
+		assert_eq!(
+			simulation.summary(),
+			SimulationSummary {
+				ticks: 4,
+				demand: 400,
+				supplied: 250,
+				unserved: 150,
+				emissions: 47,
+				stock_outs: 2,
+			}
+		);
+	}
+
+	#[test]
+	fn csv_export() {
+		let gas = DynamicFuel::new("gas", 100).with_carbon_intensity(50);
+		let grid = PowerGrid::new().with_fuel(gas.clone(), 5).with_plant(
+			"gas, unit 1",
+			RuntimeGenerator::new(100),
+			gas,
+			1,
+		);
+		let mut simulation = Simulation::new(grid).with_consumer(Constant(150));
+		simulation.run(2);
+
+		let mut csv = Vec::new();
+		simulation.history()[0].report.to_csv(&mut csv).unwrap();
+		assert_eq!(
+			String::from_utf8(csv).unwrap(),
+			"plant,fuel_burned,output,emissions\n\"gas, unit 1\",1,100,5\n"
+		);
+
+		let mut csv = Vec::new();
+		simulation.history().to_csv(&mut csv).unwrap();
+		let csv = String::from_utf8(csv).unwrap();
+		let lines = csv.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 3);
+		assert_eq!(lines[2], "1,150,100,0,0,50,0,5");
+	}
+
+	#[cfg(feature = "stochastic")]
+	#[test]
+	fn seeded_noise_and_outages() {
+		let mut rng = SeededRng::new(42);
+		let first = (0..4).map(|_| rng.next_u64()).collect::<Vec<_>>();
+		let mut rng = SeededRng::new(42);
+		assert_eq!((0..4).map(|_| rng.next_u64()).collect::<Vec<_>>(), first);
+
+		let noisy = Noisy {
+			consumer: Constant(1000),
+			seed: 7,
+			percent: 10,
+		};
+		let demand = (0..100).map(|t| noisy.demand_at(t)).collect::<Vec<_>>();
+		assert!(demand.iter().all(|d| (900..=1100).contains(d)));
+		assert!(demand.iter().any(|&d| d != 1000));
+		assert_eq!(noisy.demand_at(57), demand[57]);
+
+		let gas = DynamicFuel::new("gas", 1);
+		let outputs = |seed| {
+			let mut grid = PowerGrid::new()
+				.with_fuel(gas.clone(), 10_000)
+				.with_plant("gas", RuntimeGenerator::new(100), gas.clone(), 10)
+				.with_strategy(WithOutages::new(MeritOrder, seed, 30));
+			(0..100)
+				.map(|_| grid.dispatch(10).supplied())
+				.collect::<Vec<_>>()
+		};
+		let run = outputs(1);
+		let outages = run.iter().filter(|&&o| o == 0).count();
+		assert!((15..=45).contains(&outages), "{outages} outages");
+		assert_eq!(outputs(1), run);
+		assert_ne!(outputs(2), run);
+	}
+
+	#[test]
+	fn emissions_cap() {
+		let coal = DynamicFuel::new("coal", 100).with_carbon_intensity(100);
+		let wind = DynamicFuel::new("wind", 100);
+		let mut grid = PowerGrid::new()
+			.with_fuel(coal.clone(), 1000)
+			.with_fuel(wind.clone(), 1000)
+			.with_plant("coal", RuntimeGenerator::new(100), coal, 3)
+			.with_plant("wind", RuntimeGenerator::new(100), wind, 1)
+			.with_emissions_cap(EmissionsCap {
+				grams: 50,
+				period: 3,
+			});
+
+		// 30 grams, then 60, at which point coal is excluded for the rest of the period.
+		let shortfalls = (0..4)
+			.map(|_| {
+				let report = grid.dispatch(300);
+				(report.plants[0].output, report.shortfall, report.capped)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			shortfalls,
+			[
+				(300, 0, false),
+				(300, 0, false),
+				(0, 200, true),
+				(300, 0, false)
+			]
+		);
+		assert_eq!(grid.period_emissions(), 30);
+	}
+
+	#[test]
+	fn topology_flows_and_losses() {
+		// north -10%- city -50%- south -10%- far, and far -20%- city.
+		let topology = Topology::new("city")
+			.with_line("north", "city", Percent::new(10))
+			.with_line("city", "south", Percent::new(50))
+			.with_line("south", "far", Percent::new(10))
+			.with_line("far", "city", Percent::new(20))
+			.with_line("island", "atoll", Percent::new(0))
+			.with_plant_at("north", "north")
+			.with_plant_at("south", "south")
+			.with_plant_at("island", "island");
+		let fuel = DynamicFuel::new("fuel", 100);
+		let mut grid = PowerGrid::new()
+			.with_fuel(fuel.clone(), 1000)
+			.with_plant("north", RuntimeGenerator::new(100), fuel.clone(), 1)
+			.with_plant("island", RuntimeGenerator::new(100), fuel.clone(), 10)
+			.with_plant("south", RuntimeGenerator::new(100), fuel.clone(), 10)
+			.with_plant("local", RuntimeGenerator::new(100), fuel, 10)
+			.with_topology(topology);
+
+		let report = grid.dispatch(300);
+		let outputs = report.plants.iter().map(|p| p.output).collect::<Vec<_>>();
+		// The island cannot reach the city, and the south goes through the far bus.
+		assert_eq!(outputs, [100, 0, 300, 0]);
+		let flow = |from: &str, to: &str, flow, loss| LineFlow {
+			from: from.to_string(),
+			to: to.to_string(),
+			flow,
+			loss,
+		};
+		assert_eq!(
+			report.flows,
+			[
+				flow("north", "city", 100, 10),
+				flow("city", "south", 0, 0),
+				flow("south", "far", 300, 30),
+				flow("far", "city", 270, 54),
+				flow("island", "atoll", 0, 0),
+			]
+		);
+		assert_eq!((report.losses(), report.delivered()), (94, 306));
+		assert_eq!((report.shortfall, report.surplus), (0, 6));
+	}
+
+	#[test]
+	fn deliveries_and_stock_outs() {
+		let coal = DynamicFuel::of::<Coal>();
+		let inventory = Inventory::<Coal>::new(1)
+			.with_delivery(2, 1)
+			.with_delivery(2, 1);
+		let grid = PowerGrid::new().with_inventory(inventory).with_plant(
+			"coal",
+			RuntimeGenerator::new(100),
+			coal,
+			1,
+		);
+		let mut simulation = Simulation::new(grid).with_consumer(Constant(1));
+
+		simulation.run(4);
+		let stock_outs = simulation
+			.stock_outs()
+			.map(|(tick, s)| (tick, s.plant.as_str(), s.fuel.as_str()))
+			.collect::<Vec<_>>();
+		assert_eq!(stock_outs, [(1, "coal", "Coal")]);
+		assert_eq!(simulation.grid().inventory("Coal"), 0);
+		assert_eq!(simulation.summary().stock_outs, 1);
+
+		let mut inventory = Inventory::<Coal>::new(0).with_delivery(5, 3);
+		assert_eq!(inventory.receive(u64::MAX), 3);
+		assert!(inventory.deliveries().is_empty());
+	}
+
+	#[test]
+	fn inventories_saturate() {
+		let mut inventory = Inventory::<Coal>::new(u32::MAX)
+			.with_delivery(0, 1)
+			.with_delivery(1, u32::MAX)
+			.with_delivery(1, u32::MAX);
+		assert_eq!(inventory.receive(0), 1);
+		assert_eq!(inventory.receive(1), u32::MAX);
+		assert_eq!(inventory.amount(), u32::MAX);
+
+		let coal = DynamicFuel::of::<Coal>();
+		let mut grid = PowerGrid::new()
+			.with_inventory(Inventory::<Coal>::new(u32::MAX).with_delivery(0, 1))
+			.with_plant("coal", RuntimeGenerator::new(100), coal.clone(), 1);
+		grid.schedule_delivery(coal.clone(), 0, u32::MAX);
+		grid.stock(coal, 1);
+		grid.dispatch(0);
+		assert_eq!(grid.inventory("Coal"), u32::MAX);
+	}
+
+	#[test]
+	fn plants_wear_by_fuel_burned() {
+		let diesel = DynamicFuel::of::<Diesel>();
+		let mut grid = PowerGrid::new()
+			.with_fuel(diesel.clone(), 100)
+			.with_plant("diesel", WearByFuel::<10>::new(90), diesel, 10)
+			.with_event_log();
+
+		let outputs = (0..3)
+			.map(|_| grid.dispatch(10_000).supplied())
+			.collect::<Vec<_>>();
+		assert_eq!(outputs, [900, 890, 880]);
+		let efficiencies = grid
+			.event_log()
+			.unwrap()
+			.events()
+			.iter()
+			.map(|e| e.efficiency)
+			.collect::<Vec<_>>();
+		assert_eq!(efficiencies, [90, 89, 88]);
+	}
+}
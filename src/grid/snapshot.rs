@@ -0,0 +1,570 @@
+//! Snapshots of a [`Simulation`], such that long runs can be checkpointed to disk and resumed.
+//!
+//! The plants, strategy and consumers of a simulation are trait objects that cannot be rebuilt from
+//! bytes, so only simulations built from a [`Scenario`] can be snapshotted: the snapshot holds the
+//! scenario, followed by everything that changed since the simulation was built. That is the tick
+//! and history, the inventories and their deliveries, the state of every plant, the charge of the
+//! storage, the event log and the progress through the period of the emissions cap.
+//!
+//! All integers are stored in little endian, and all sequences are prefixed by their length.
+
+use super::*;
+use crate::h_advanced_traits::{Checkpoint, RuntimeGenerator};
+use crate::scenario::{ConsumerSpec, PlantSpec, Scenario, StrategySpec};
+
+/// Every snapshot starts with these bytes, followed by the version of its format.
+const MAGIC: &[u8; 4] = b"PBAS";
+const VERSION: u8 = 1;
+
+/// Why a snapshot could not be taken or restored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+	/// The simulation was not built from a [`Scenario`].
+	NotFromScenario,
+	/// The bytes are not a snapshot, or one of another version.
+	UnknownFormat,
+	/// The snapshot ends early.
+	Truncated,
+	/// The snapshot holds a value that does not fit the simulation.
+	Invalid(&'static str),
+}
+
+impl std::fmt::Display for SnapshotError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SnapshotError::NotFromScenario => write!(f, "simulation not built from a scenario"),
+			SnapshotError::UnknownFormat => write!(f, "not a snapshot of this version"),
+			SnapshotError::Truncated => write!(f, "snapshot ends early"),
+			SnapshotError::Invalid(what) => write!(f, "invalid {what} in snapshot"),
+		}
+	}
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl Simulation {
+	/// Remember the scenario this simulation was built from, such that it can be snapshotted.
+	pub(crate) fn with_scenario(mut self, scenario: Scenario) -> Self {
+		self.scenario = Some(scenario);
+		self
+	}
+
+	/// Capture the full state of the simulation, see [`Simulation::restore`].
+	pub fn snapshot(&self) -> Result<Vec<u8>, SnapshotError> {
+		let scenario = self
+			.scenario
+			.as_ref()
+			.ok_or(SnapshotError::NotFromScenario)?;
+		let grid = &self.grid;
+
+		let mut out = MAGIC.to_vec();
+		out.push(VERSION);
+		scenario.encode(&mut out);
+		self.tick.encode(&mut out);
+		self.history.encode(&mut out);
+		grid.stocks.encode(&mut out);
+		// The plants of a scenario are run by runtime generators, whose checkpoint is their
+		// efficiency.
+		let plants = grid.plants.iter().map(|p| p.provider.efficiency());
+		plants.collect::<Vec<_>>().encode(&mut out);
+		grid.storage.encode(&mut out);
+		grid.log.encode(&mut out);
+		grid.period_emissions.encode(&mut out);
+		grid.dispatches.encode(&mut out);
+		Ok(out)
+	}
+
+	/// Resume a simulation from a [`Simulation::snapshot`], exactly where it was taken.
+	///
+	/// ```
+	/// use pba_qualifier_exam::grid::*;
+	/// use pba_qualifier_exam::scenario::Scenario;
+	///
+	/// # fn main() {
+	/// let scenario = Scenario::from_toml(
+	///     r#"
+	///     [[fuel]]
+	///     name = "Coal"
+	///     stock = 100
+	///
+	///     [[plant]]
+	///     name = "coal"
+	///     fuel = "Coal"
+	///     efficiency = 40
+	///     max_fuel = 10
+	///
+	///     [[consumer]]
+	///     profile = "constant"
+	///     demand = 500
+	///     "#,
+	/// )
+	/// .unwrap();
+	/// let mut simulation = scenario.build().unwrap();
+	/// simulation.run(3);
+	///
+	/// let mut resumed = Simulation::restore(&simulation.snapshot().unwrap()).unwrap();
+	/// assert_eq!(resumed.tick(), 3);
+	/// assert_eq!(resumed.run(5), simulation.run(5));
+	/// # }
+	/// ```
+	pub fn restore(bytes: &[u8]) -> Result<Self, SnapshotError> {
+		let mut input = bytes;
+		if input.get(..MAGIC.len()) != Some(&MAGIC[..]) || input.get(MAGIC.len()) != Some(&VERSION)
+		{
+			return Err(SnapshotError::UnknownFormat);
+		}
+		input = &input[MAGIC.len() + 1..];
+
+		let scenario = Scenario::decode(&mut input)?;
+		let mut simulation = scenario
+			.build()
+			.map_err(|_| SnapshotError::Invalid("scenario"))?;
+		simulation.tick = Decode::decode(&mut input)?;
+		simulation.history = Decode::decode(&mut input)?;
+
+		let grid = &mut simulation.grid;
+		let stocks = Vec::<Stock>::decode(&mut input)?;
+		// The stocks of the scenario come first, then those stocked since.
+		let same_fuels = stocks.len() >= grid.stocks.len()
+			&& stocks.iter().zip(&grid.stocks).all(|(restored, built)| {
+				restored.fuel.name() == built.fuel.name()
+					&& restored.fuel.density() == built.fuel.density()
+			});
+		if !same_fuels {
+			return Err(SnapshotError::Invalid("inventories"));
+		}
+		grid.stocks = stocks;
+		let efficiencies = Vec::<u8>::decode(&mut input)?;
+		if efficiencies.len() != grid.plants.len() {
+			return Err(SnapshotError::Invalid("plants"));
+		}
+		for (plant, efficiency) in grid.plants.iter_mut().zip(efficiencies) {
+			plant.provider = Box::new(RuntimeGenerator::restore(efficiency));
+		}
+		grid.storage = Decode::decode(&mut input)?;
+		grid.log = Decode::decode(&mut input)?;
+		grid.period_emissions = Decode::decode(&mut input)?;
+		grid.dispatches = Decode::decode(&mut input)?;
+		if scenario.strategy == StrategySpec::RoundRobin {
+			// A round robin starts one plant further at every dispatch.
+			let next = grid.dispatches % grid.plants.len().max(1) as u64;
+			grid.strategy = Box::new(RoundRobin {
+				next: next as usize,
+			});
+		}
+
+		if !input.is_empty() {
+			return Err(SnapshotError::Invalid("trailing bytes"));
+		}
+		Ok(simulation)
+	}
+}
+
+/// Something that can be written into a snapshot.
+trait Encode {
+	fn encode(&self, out: &mut Vec<u8>);
+}
+
+/// Something that can be read back from a snapshot, advancing `input` past it.
+trait Decode: Sized {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError>;
+}
+
+/// Take the next `N` bytes of `input`.
+fn take<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], SnapshotError> {
+	if input.len() < N {
+		return Err(SnapshotError::Truncated);
+	}
+	let (bytes, rest) = input.split_at(N);
+	*input = rest;
+	Ok(bytes.try_into().expect("exactly N bytes were split off"))
+}
+
+macro_rules! integers {
+	($($int:ty),*) => {
+		$(
+			impl Encode for $int {
+				fn encode(&self, out: &mut Vec<u8>) {
+					out.extend_from_slice(&self.to_le_bytes());
+				}
+			}
+
+			impl Decode for $int {
+				fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+					take(input).map(<$int>::from_le_bytes)
+				}
+			}
+		)*
+	};
+}
+
+integers!(u8, u32, u64);
+
+impl Encode for bool {
+	fn encode(&self, out: &mut Vec<u8>) {
+		(*self as u8).encode(out);
+	}
+}
+
+impl Decode for bool {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		match u8::decode(input)? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err(SnapshotError::Invalid("boolean")),
+		}
+	}
+}
+
+/// The length of a sequence.
+fn decode_len(input: &mut &[u8]) -> Result<usize, SnapshotError> {
+	let len = u64::decode(input)?;
+	// Every element takes at least a byte, which keeps a corrupted length from allocating wildly.
+	if len > input.len() as u64 {
+		return Err(SnapshotError::Truncated);
+	}
+	Ok(len as usize)
+}
+
+impl Encode for String {
+	fn encode(&self, out: &mut Vec<u8>) {
+		(self.len() as u64).encode(out);
+		out.extend_from_slice(self.as_bytes());
+	}
+}
+
+impl Decode for String {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		let len = decode_len(input)?;
+		let (bytes, rest) = input.split_at(len);
+		*input = rest;
+		String::from_utf8(bytes.to_vec()).map_err(|_| SnapshotError::Invalid("string"))
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode(&self, out: &mut Vec<u8>) {
+		(self.len() as u64).encode(out);
+		for item in self {
+			item.encode(out);
+		}
+	}
+}
+
+impl<T: Decode> Decode for Vec<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		let len = decode_len(input)?;
+		(0..len).map(|_| T::decode(input)).collect()
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode(&self, out: &mut Vec<u8>) {
+		self.is_some().encode(out);
+		if let Some(value) = self {
+			value.encode(out);
+		}
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		match bool::decode(input)? {
+			true => T::decode(input).map(Some),
+			false => Ok(None),
+		}
+	}
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+	fn encode(&self, out: &mut Vec<u8>) {
+		self.0.encode(out);
+		self.1.encode(out);
+	}
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		Ok((A::decode(input)?, B::decode(input)?))
+	}
+}
+
+impl Encode for BTreeMap<u64, u32> {
+	fn encode(&self, out: &mut Vec<u8>) {
+		(self.len() as u64).encode(out);
+		for (tick, amount) in self {
+			(*tick, *amount).encode(out);
+		}
+	}
+}
+
+impl Decode for BTreeMap<u64, u32> {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		Ok(Vec::<(u64, u32)>::decode(input)?.into_iter().collect())
+	}
+}
+
+/// Encode and decode a struct field by field, in the given order.
+macro_rules! record {
+	($($ty:ident { $($field:ident),* $(,)? })*) => {
+		$(
+			impl Encode for $ty {
+				fn encode(&self, out: &mut Vec<u8>) {
+					$(self.$field.encode(out);)*
+				}
+			}
+
+			impl Decode for $ty {
+				fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+					Ok(Self {
+						$($field: Decode::decode(input)?,)*
+					})
+				}
+			}
+		)*
+	};
+}
+
+record! {
+	DynamicFuel { name, density, cents_per_kbtu, grams_per_kbtu }
+	PlantSpec { name, fuel, efficiency, max_fuel }
+	EmissionsCap { grams, period }
+	Diurnal { base, amplitude, period }
+	Step { before, after, at }
+	Scenario { ticks, fuels, plants, storage, emissions_cap, consumers, strategy }
+	Stock { fuel, inventory }
+	ProvisionEvent { tick, provider, fuel, amount, efficiency, output }
+	EventLog { events, tick }
+	PlantDispatch { plant, fuel_burned, output, emissions }
+	LineFlow { from, to, flow, loss }
+	StockOut { plant, fuel }
+	PlantFailure { plant, error }
+	DispatchReport {
+		demand,
+		plants,
+		discharged,
+		charged,
+		shortfall,
+		surplus,
+		capped,
+		flows,
+		stock_outs,
+		failures,
+	}
+	TickResult { tick, report }
+}
+
+impl Encode for Storage {
+	fn encode(&self, out: &mut Vec<u8>) {
+		self.capacity.encode(out);
+		self.stored.encode(out);
+		self.efficiency.get().encode(out);
+		self.losses.encode(out);
+	}
+}
+
+impl Decode for Storage {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		let storage = Storage {
+			capacity: Decode::decode(input)?,
+			stored: Decode::decode(input)?,
+			efficiency: Percent::new(Decode::decode(input)?),
+			losses: Decode::decode(input)?,
+		};
+		if storage.stored > storage.capacity {
+			return Err(SnapshotError::Invalid("storage"));
+		}
+		Ok(storage)
+	}
+}
+
+impl Encode for Inventory<RuntimeFuel> {
+	fn encode(&self, out: &mut Vec<u8>) {
+		self.amount().encode(out);
+		self.deliveries.encode(out);
+	}
+}
+
+impl Decode for Inventory<RuntimeFuel> {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		let mut inventory = Inventory::new(Decode::decode(input)?);
+		inventory.deliveries = Decode::decode(input)?;
+		Ok(inventory)
+	}
+}
+
+impl Encode for ProvisionError {
+	fn encode(&self, out: &mut Vec<u8>) {
+		let tag: u8 = match self {
+			ProvisionError::CoolingDown => 0,
+			ProvisionError::Fault => 1,
+		};
+		tag.encode(out);
+	}
+}
+
+impl Decode for ProvisionError {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		match u8::decode(input)? {
+			0 => Ok(ProvisionError::CoolingDown),
+			1 => Ok(ProvisionError::Fault),
+			_ => Err(SnapshotError::Invalid("provision error")),
+		}
+	}
+}
+
+impl Encode for ConsumerSpec {
+	fn encode(&self, out: &mut Vec<u8>) {
+		match self {
+			ConsumerSpec::Constant(Constant(demand)) => {
+				0u8.encode(out);
+				demand.encode(out);
+			}
+			ConsumerSpec::Diurnal(diurnal) => {
+				1u8.encode(out);
+				diurnal.encode(out);
+			}
+			ConsumerSpec::Step(step) => {
+				2u8.encode(out);
+				step.encode(out);
+			}
+		}
+	}
+}
+
+impl Decode for ConsumerSpec {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		match u8::decode(input)? {
+			0 => Ok(ConsumerSpec::Constant(Constant(Decode::decode(input)?))),
+			1 => Ok(ConsumerSpec::Diurnal(Decode::decode(input)?)),
+			2 => Ok(ConsumerSpec::Step(Decode::decode(input)?)),
+			_ => Err(SnapshotError::Invalid("consumer")),
+		}
+	}
+}
+
+impl Encode for StrategySpec {
+	fn encode(&self, out: &mut Vec<u8>) {
+		let tag: u8 = match self {
+			StrategySpec::MeritOrder => 0,
+			StrategySpec::LeastCost => 1,
+			StrategySpec::LeastCarbon => 2,
+			StrategySpec::RoundRobin => 3,
+		};
+		tag.encode(out);
+	}
+}
+
+impl Decode for StrategySpec {
+	fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+		match u8::decode(input)? {
+			0 => Ok(StrategySpec::MeritOrder),
+			1 => Ok(StrategySpec::LeastCost),
+			2 => Ok(StrategySpec::LeastCarbon),
+			3 => Ok(StrategySpec::RoundRobin),
+			_ => Err(SnapshotError::Invalid("strategy")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SCENARIO: &str = r#"
+		strategy = "round-robin"
+
+		[[fuel]]
+		name = "Coal"
+		stock = 30
+
+		[[fuel]]
+		name = "biogas"
+		density = 90
+		carbon = 10
+		stock = 20
+
+		[[plant]]
+		name = "coal"
+		fuel = "Coal"
+		efficiency = 40
+		max_fuel = 4
+
+		[[plant]]
+		name = "biogas"
+		fuel = "biogas"
+		efficiency = 80
+		max_fuel = 3
+
+		[storage]
+		capacity = 1000
+		efficiency = 90
+
+		[emissions_cap]
+		grams = 50000
+		period = 4
+
+		[[consumer]]
+		profile = "diurnal"
+		base = 400
+		amplitude = 300
+		period = 6
+	"#;
+
+	#[test]
+	fn resuming_from_a_snapshot() {
+		let mut simulation = Scenario::from_toml(SCENARIO).unwrap().build().unwrap();
+		simulation
+			.grid_mut()
+			.schedule_delivery(DynamicFuel::new("biogas", 90), 7, 10);
+		simulation.run(5);
+
+		let snapshot = simulation.snapshot().unwrap();
+		let mut resumed = Simulation::restore(&snapshot).unwrap();
+		assert_eq!(resumed.history(), simulation.history());
+		assert_eq!(resumed.grid().storage(), simulation.grid().storage());
+		assert_eq!(resumed.snapshot().unwrap(), snapshot);
+
+		assert_eq!(resumed.run(10), simulation.run(10));
+		assert_eq!(resumed.summary(), simulation.summary());
+		assert_eq!(resumed.grid().inventory("biogas"), 0);
+	}
+
+	#[test]
+	fn invalid_snapshots() {
+		let simulation = Simulation::new(PowerGrid::new());
+		assert_eq!(simulation.snapshot(), Err(SnapshotError::NotFromScenario));
+
+		let snapshot = Scenario::from_toml(SCENARIO)
+			.unwrap()
+			.build()
+			.unwrap()
+			.snapshot()
+			.unwrap();
+		let restore = |bytes: &[u8]| Simulation::restore(bytes).err();
+		assert_eq!(
+			restore(b"not a snapshot"),
+			Some(SnapshotError::UnknownFormat)
+		);
+		assert_eq!(
+			restore(&snapshot[..snapshot.len() - 1]),
+			Some(SnapshotError::Truncated)
+		);
+		assert_eq!(
+			restore(&[&snapshot[..], &[0]].concat()),
+			Some(SnapshotError::Invalid("trailing bytes"))
+		);
+
+		let mut simulation = Scenario::from_toml(SCENARIO).unwrap().build().unwrap();
+		simulation.grid.stocks[1].fuel = DynamicFuel::new("biogas", 9000);
+		assert_eq!(
+			restore(&simulation.snapshot().unwrap()),
+			Some(SnapshotError::Invalid("inventories"))
+		);
+		simulation.grid.stocks.swap(0, 1);
+		assert_eq!(
+			restore(&simulation.snapshot().unwrap()),
+			Some(SnapshotError::Invalid("inventories"))
+		);
+	}
+}
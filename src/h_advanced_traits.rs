@@ -4,6 +4,44 @@
 
 use std::marker::PhantomData;
 
+/// Keeps the renewability of a fuel next to its definition:
+///
+/// ```
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// #[renewable(score = 90)]
+/// pub struct Biogas;
+/// impl Fuel for Biogas {
+///     type Output = BTU;
+///     fn energy_density() -> BTU {
+///         110
+///     }
+/// }
+///
+/// # fn main() {
+/// assert_eq!(Biogas::score(), 90);
+/// let engine = GreenEngine::<Biogas>(std::marker::PhantomData);
+/// assert_eq!(engine.provide_energy(FuelContainer::new(10)), 1100);
+/// # }
+/// ```
+///
+/// A score too low for a renewable fuel is rejected:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// #[renewable(score = 10)]
+/// pub struct Peat;
+/// impl Fuel for Peat {
+///     type Output = BTU;
+///     fn energy_density() -> BTU {
+///         90
+///     }
+/// }
+/// # fn main() {}
+/// ```
+pub use pba_qualifier_exam_macros::renewable;
+
 // You may uncomment and use the following import if you need it. You may also read its
 // documentation at https://doc.rust-lang.org/std/cell/struct.RefCell.html
 // use std::cell::RefCell;
@@ -50,6 +88,11 @@ pub trait Fuel {
 
 	/// The amount of energy contained in a single unit of fuel.
 	fn energy_density() -> Self::Output;
+
+	/// A human readable name of this fuel, used in reports. Defaults to the full type name.
+	fn name() -> &'static str {
+		std::any::type_name::<Self>()
+	}
 }
 
 pub struct Diesel;
@@ -59,6 +102,9 @@ impl Fuel for Diesel {
 		let btu_val = 100;
 		btu_val.into()
 	}
+	fn name() -> &'static str {
+		"Diesel"
+	}
 }
 
 pub struct LithiumBattery;
@@ -68,6 +114,9 @@ impl Fuel for LithiumBattery {
 		let btu_val: u32 = 200;
 		btu_val.into()
 	}
+	fn name() -> &'static str {
+		"LithiumBattery"
+	}
 }
 
 pub struct Uranium;
@@ -77,12 +126,77 @@ impl Fuel for Uranium {
 		let btu_val = 1000;
 		btu_val.into()
 	}
+	fn name() -> &'static str {
+		"Uranium"
+	}
+}
+
+#[renewable(score = 60)]
+pub struct Ethanol;
+impl Fuel for Ethanol {
+	type Output = Joule;
+	fn energy_density() -> Self::Output {
+		let btu_val = 80;
+		btu_val.into()
+	}
+	fn name() -> &'static str {
+		"Ethanol"
+	}
+}
+
+pub struct Hydrogen;
+impl Fuel for Hydrogen {
+	type Output = BTU;
+	fn energy_density() -> Self::Output {
+		300
+	}
+	fn name() -> &'static str {
+		"Hydrogen"
+	}
+}
+
+pub struct Coal;
+impl Fuel for Coal {
+	type Output = Joule;
+	fn energy_density() -> Self::Output {
+		let btu_val = 250;
+		btu_val.into()
+	}
+	fn name() -> &'static str {
+		"Coal"
+	}
+}
+
+pub struct NaturalGas;
+impl Fuel for NaturalGas {
+	type Output = BTU;
+	fn energy_density() -> Self::Output {
+		120
+	}
+	fn name() -> &'static str {
+		"NaturalGas"
+	}
+}
+
+pub struct Gasoline;
+impl Fuel for Gasoline {
+	type Output = Joule;
+	fn energy_density() -> Self::Output {
+		let btu_val = 110;
+		btu_val.into()
+	}
+	fn name() -> &'static str {
+		"Gasoline"
+	}
 }
 
 /// A container for any fuel type.
 pub struct FuelContainer<F: Fuel> {
 	/// The amount of fuel.
 	amount: u32,
+	/// The energy density of the fuel, in BTU, if it is only known at runtime. See
+	/// [`RuntimeFuel`].
+	density: Option<BTU>,
 	/// NOTE: Fuel doesn't really have any methods that require `&self` on it,
 	/// so any information that we can get, we can get from `F` as **TYPE**, we don't really need
 	/// to store an instance of `F`, like `fuel: F` as a struct field. But to satisfy the compiler,
@@ -95,9 +209,36 @@ impl<F: Fuel> FuelContainer<F> {
 	pub fn new(amount: u32) -> Self {
 		Self {
 			amount,
+			density: None,
 			_marker: Default::default(),
 		}
 	}
+
+	/// The amount of fuel left in this container.
+	pub fn amount(&self) -> u32 {
+		self.amount
+	}
+
+	/// The energy contained in a single unit of the fuel, in BTU.
+	pub fn energy_density(&self) -> BTU {
+		self.density.unwrap_or_else(|| F::energy_density().into())
+	}
+
+	/// Move `amount` units of fuel out of this container into a new one, unless there is not
+	/// enough left, in which case nothing is taken.
+	pub fn try_take(&mut self, amount: u32) -> Option<Self> {
+		self.amount = self.amount.checked_sub(amount)?;
+		Some(self.with_amount(amount))
+	}
+
+	/// A container of `amount` units of the same fuel.
+	fn with_amount(&self, amount: u32) -> Self {
+		Self {
+			amount,
+			density: self.density,
+			_marker: PhantomData,
+		}
+	}
 }
 
 /// Something that can provide energy from a given `F` fuel type, like a power-plant.
@@ -132,16 +273,156 @@ pub trait ProvideEnergy<F: Fuel> {
 	fn provide_energy_ideal(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
 		f.amount.into()
 	}
+
+	/// Provide energy `times` times in a row, burning `amount_per_call` units of fuel each time,
+	/// and return the total. Each provision goes through [`ProvideEnergy::provide_energy`], so
+	/// stateful providers wear down as they would when called in a loop.
+	fn provide_energy_n(&self, amount_per_call: u32, times: u32) -> BTU {
+		(0..times)
+			.map(|_| {
+				self.provide_energy(FuelContainer::new(amount_per_call))
+					.into()
+			})
+			.sum()
+	}
+
+	/// Same as [`ProvideEnergy::provide_energy`], but also reports the energy contained in the fuel
+	/// that was not turned into useful output, as waste heat.
+	///
+	/// As with all the energy accounting here, the useful output is passed through BTU.
+	fn provide_energy_detailed(&self, f: FuelContainer<F>) -> DetailedEnergy<<F as Fuel>::Output> {
+		let contained = scale_energy(f.energy_density(), f.amount, 1, 1);
+		let useful: BTU = self.provide_energy(f).into();
+		DetailedEnergy {
+			useful: useful.into(),
+			waste_heat: contained.saturating_sub(useful),
+		}
+	}
+
+	/// The efficiency, in percent, that this provider would apply to the next provision. Used to
+	/// compare providers without burning any fuel.
+	///
+	/// Defaults to 100, which is right for perfectly efficient providers. Providers whose
+	/// efficiency depends on the amount of fuel burned report it for a single unit of fuel.
+	fn current_efficiency(&self) -> u8 {
+		100
+	}
+
+	/// The energy, in BTU, this provider would currently extract from a single unit of `F`.
+	fn effective_density(&self) -> BTU {
+		let density = F::energy_density().into();
+		scale_energy(density, 1, self.current_efficiency() as u32, 100)
+	}
+
+	/// Burn only `amount` units of the fuel in `f`, leaving the rest in the container. If the
+	/// container holds less than `amount`, whatever is left is burned.
+	fn provide_energy_from(&self, f: &mut FuelContainer<F>, amount: u32) -> <F as Fuel>::Output {
+		let amount = amount.min(f.amount);
+		f.amount -= amount;
+		self.provide_energy(f.with_amount(amount))
+	}
+
+	/// Same as [`ProvideEnergy::provide_energy`], but allows the provider to refuse the provision.
+	///
+	/// By default, providers never fail. The wrappers of this module forward the failures of the
+	/// providers they wrap.
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		Ok(self.provide_energy(f))
+	}
+
+	/// Provide energy from a whole batch of containers, spreading them across all available
+	/// threads. The outputs of the individual containers are summed up in BTU.
+	///
+	/// Only available for providers that can be shared across threads, see
+	/// [`SyncInternalCombustion`] for a thread-safe stateful engine.
+	fn provide_energy_par_batch(&self, batch: Vec<FuelContainer<F>>) -> <F as Fuel>::Output
+	where
+		Self: Sync,
+		F: Send,
+	{
+		let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+		let chunk_size = ((batch.len() + threads - 1) / threads).max(1);
+		let mut containers = batch.into_iter();
+
+		let total: BTU = std::thread::scope(|s| {
+			let mut handles = Vec::new();
+			loop {
+				let chunk = containers.by_ref().take(chunk_size).collect::<Vec<_>>();
+				if chunk.is_empty() {
+					break;
+				}
+				handles.push(s.spawn(move || {
+					chunk
+						.into_iter()
+						.map(|f| self.provide_energy(f).into())
+						.sum::<BTU>()
+				}));
+			}
+			handles.into_iter().map(|h| h.join().unwrap()).sum()
+		});
+
+		total.into()
+	}
+}
+
+// Providers behind references and smart pointers are providers as well, so they can be shared or
+// stored without any extra wrapper.
+macro_rules! impl_provide_energy_for_pointer {
+	($($pointer:ty),*) => {
+		$(
+			impl<F: Fuel, T: ProvideEnergy<F> + ?Sized> ProvideEnergy<F> for $pointer {
+				fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+					(**self).provide_energy(f)
+				}
+
+				fn try_provide_energy(
+					&self,
+					f: FuelContainer<F>,
+				) -> Result<<F as Fuel>::Output, ProvisionError> {
+					(**self).try_provide_energy(f)
+				}
+
+				fn current_efficiency(&self) -> u8 {
+					(**self).current_efficiency()
+				}
+			}
+		)*
+	};
+}
+
+impl_provide_energy_for_pointer!(&T, Box<T>, std::rc::Rc<T>, std::sync::Arc<T>);
+
+/// The outcome of [`ProvideEnergy::provide_energy_detailed`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct DetailedEnergy<O> {
+	/// The energy actually provided.
+	pub useful: O,
+	/// The rest of the energy contained in the fuel, lost as heat.
+	pub waste_heat: BTU,
+}
+
+/// The reasons why [`ProvideEnergy::try_provide_energy`] may fail. The fuel is lost either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvisionError {
+	/// The provider has been used too recently, see [`Cooldown`].
+	CoolingDown,
+	/// An injected failure, see `Flaky`.
+	Fault,
 }
 
 /// A nuclear reactor that can only consume `Uranium` and provide energy with 99% efficiency.
 pub struct NuclearReactor;
 impl<F: Fuel> ProvideEnergy<F> for NuclearReactor {
 	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
-		let density: Joule =  Joule(F::energy_density().into());
-		let result = density.0 as f32 * f.amount as f32 * 0.99;
-		
-	    (result as u32).into()
+		let density = f.energy_density();
+		scale_energy(density, f.amount, 99, 100).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		99
 	}
 }
 
@@ -150,46 +431,261 @@ impl<F: Fuel> ProvideEnergy<F> for NuclearReactor {
 /// The `DECAY` const must be interpreted as such: per every `DECAY` times `provide_energy` is
 /// called on an instance of this type, the efficiency should reduce by one. The initial efficiency
 /// must be configurable with a `fn new(efficiency: u8) -> Self`.
-pub struct InternalCombustion<const DECAY: u32>(core::cell::Cell<u8>);
+///
+/// How exactly the engine wears down is decided by the [`DecayPolicy`] `D`.
+pub struct InternalCombustion<const DECAY: u32, D: DecayPolicy = LinearByCalls> {
+	initial_efficiency: u8,
+	efficiency: core::cell::Cell<u8>,
+	calls: core::cell::Cell<u32>,
+	fuel_burned: core::cell::Cell<u64>,
+	_policy: PhantomData<D>,
+}
+
+impl<const DECAY: u32, D: DecayPolicy> InternalCombustion<DECAY, D> {
+	pub fn new(efficiency: u8) -> Self {
+		Self {
+			initial_efficiency: efficiency,
+			efficiency: core::cell::Cell::new(efficiency),
+			calls: Default::default(),
+			fuel_burned: Default::default(),
+			_policy: PhantomData,
+		}
+	}
+
+	/// The efficiency that will be applied to the next provision.
+	pub fn efficiency(&self) -> u8 {
+		self.efficiency.get()
+	}
+
+	/// A brand new engine with the same configuration as this one, as if no energy was ever
+	/// provided. Use `clone` instead to get a copy that is as worn down as this engine.
+	pub fn fresh(&self) -> Self {
+		Self::new(self.initial_efficiency)
+	}
+}
+
+/// Clones the current state of the engine, including its wear. See also
+/// [`InternalCombustion::fresh`].
+impl<const DECAY: u32, D: DecayPolicy> Clone for InternalCombustion<DECAY, D> {
+	fn clone(&self) -> Self {
+		Self {
+			initial_efficiency: self.initial_efficiency,
+			efficiency: self.efficiency.clone(),
+			calls: self.calls.clone(),
+			fuel_burned: self.fuel_burned.clone(),
+			_policy: PhantomData,
+		}
+	}
+}
+
+impl<const DECAY: u32, D: DecayPolicy, F: Fuel> ProvideEnergy<F> for InternalCombustion<DECAY, D> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let density = f.energy_density();
+		let current = self.efficiency.get();
+
+		self.calls.set(self.calls.get() + 1);
+		self.fuel_burned
+			.set(self.fuel_burned.get() + f.amount as u64);
+		self.efficiency.set(D::next_efficiency(
+			current,
+			self.calls.get(),
+			self.fuel_burned.get(),
+			f.amount,
+		));
+
+		scale_energy(density, f.amount, current.min(100) as u32, 100).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.efficiency().min(100)
+	}
+}
+
+/// Decides how a combustion engine wears down.
+pub trait DecayPolicy {
+	/// The efficiency after a provision that burned `amount` units of fuel, given the efficiency
+	/// `current` that was applied to it. `calls` and `fuel_burned` are the lifetime totals,
+	/// including this provision.
+	fn next_efficiency(current: u8, calls: u32, fuel_burned: u64, amount: u32) -> u8;
+}
+
+/// Loses one point of efficiency per provision, saturating at zero. Efficiencies above 100 fall
+/// faster, by ten points per provision.
+pub struct LinearByCalls;
+impl DecayPolicy for LinearByCalls {
+	fn next_efficiency(current: u8, _calls: u32, _fuel_burned: u64, _amount: u32) -> u8 {
+		decayed_efficiency(current)
+	}
+}
+
+/// Loses one point of efficiency per `UNITS_PER_POINT` units of fuel burned, saturating at zero.
+pub struct LinearByFuel<const UNITS_PER_POINT: u32>;
+impl<const UNITS_PER_POINT: u32> DecayPolicy for LinearByFuel<UNITS_PER_POINT> {
+	fn next_efficiency(current: u8, _calls: u32, fuel_burned: u64, amount: u32) -> u8 {
+		let units = UNITS_PER_POINT.max(1) as u64;
+		let wear = fuel_burned / units - (fuel_burned - amount as u64) / units;
+		current.saturating_sub(wear.min(u8::MAX as u64) as u8)
+	}
+}
+
+/// Never wears down.
+pub struct NoDecay;
+impl DecayPolicy for NoDecay {
+	fn next_efficiency(current: u8, _calls: u32, _fuel_burned: u64, _amount: u32) -> u8 {
+		current
+	}
+}
+
+/// The efficiency of a combustion engine after one more provision, saturating at zero.
+fn decayed_efficiency(efficiency: u8) -> u8 {
+	if efficiency > 100 {
+		efficiency - 10
+	} else {
+		efficiency.saturating_sub(1)
+	}
+}
 
+/// Same as [`InternalCombustion`], but the efficiency is kept in an `AtomicU8` rather than a
+/// `Cell<u8>`. This makes the engine `Sync`, so it can be shared across threads behind an `Arc`.
+pub struct SyncInternalCombustion<const DECAY: u32>(core::sync::atomic::AtomicU8);
 
-impl<const DECAY: u32> InternalCombustion<DECAY> {
+impl<const DECAY: u32> SyncInternalCombustion<DECAY> {
 	pub fn new(efficiency: u8) -> Self {
-		Self(core::cell::Cell::new(efficiency))
+		Self(core::sync::atomic::AtomicU8::new(efficiency))
 	}
 }
 
-impl<const DECAY: u32, F: Fuel> ProvideEnergy<F> for InternalCombustion<DECAY> {
+impl<const DECAY: u32, F: Fuel> ProvideEnergy<F> for SyncInternalCombustion<DECAY> {
 	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
-		let density =  Joule(F::energy_density().into());
-		let efficiency = if self.0.get() > 100 { 1.0 } else { self.0.get() as f32 / 100.0 };
+		use core::sync::atomic::Ordering;
 
-		if self.0.get() > 100 {
-			self.0.set(self.0.get()-10);
-		} else {
-			self.0.set(self.0.get()-1);
-		}
+		let density = f.energy_density();
+		let current = self
+			.0
+			.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |e| {
+				Some(decayed_efficiency(e))
+			})
+			.unwrap_or_else(|e| e);
+		let efficiency = current.min(100) as u32;
 
-		let result = density.0 as f32 * f.amount as f32 * efficiency;
+		scale_energy(density, f.amount, efficiency, 100).into()
+	}
 
-		(result as u32).into()		
+	fn current_efficiency(&self) -> u8 {
+		self.0.load(core::sync::atomic::Ordering::SeqCst).min(100)
 	}
 }
 
+/// A combustion engine that wears down by how much fuel it burns rather than how often it is
+/// used: the efficiency drops by one point per `UNITS_PER_POINT` units of fuel consumed,
+/// saturating at zero.
+pub type WearByFuel<const UNITS_PER_POINT: u32> =
+	InternalCombustion<1, LinearByFuel<UNITS_PER_POINT>>;
+
 /// A hypothetical device that can, unlike the `InternalCombustion`, consume **any fuel** that's of
 /// type `trait Fuel`. It can provide a fixed efficiency regardless of fuel type. As before,
 /// EFFICIENCY is a u8 whose value should not exceed 100, is interpreted as a percent, and should
 /// saturate at 100% when a higher value is supplied.
+///
+/// Since `EFFICIENCY` is known at compile time, an obviously invalid value is rejected as soon as
+/// the generator is used:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let og = OmniGenerator::<200>;
+/// og.provide_energy(FuelContainer::<Diesel>::new(10));
+/// # }
+/// ```
 pub struct OmniGenerator<const EFFICIENCY: u8>;
 
+impl<const EFFICIENCY: u8> OmniGenerator<EFFICIENCY> {
+	/// Referenced in `provide_energy`, such that any `EFFICIENCY` above 100 fails to compile.
+	const VALID_EFFICIENCY: () = assert!(EFFICIENCY <= 100, "efficiency must not exceed 100");
+}
+
 // NOTE: implement `ProvideEnergy` for `OmniGenerator` using only one `impl` block.
 impl<const EFFICIENCY: u8, F: Fuel> ProvideEnergy<F> for OmniGenerator<EFFICIENCY> {
 	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
-		let density = F::energy_density().into();
-		let e: u32 = EFFICIENCY as u32;
-		let energy_provided = (density * f.amount * e / 100).into();
+		let () = Self::VALID_EFFICIENCY;
+
+		let density = f.energy_density();
+		let e: u32 = EFFICIENCY.min(100) as u32;
+
+		scale_energy(density, f.amount, e, 100).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		EFFICIENCY.min(100)
+	}
+}
+
+/// Same as [`OmniGenerator`], but with an efficiency only known at runtime, e.g. loaded from a
+/// scenario file. The efficiency saturates at 100%.
+pub struct RuntimeGenerator {
+	efficiency: u8,
+}
+
+impl RuntimeGenerator {
+	pub fn new(efficiency: u8) -> Self {
+		Self {
+			efficiency: efficiency.min(100),
+		}
+	}
+
+	pub fn efficiency(&self) -> u8 {
+		self.efficiency
+	}
+}
+
+impl<F: Fuel> ProvideEnergy<F> for RuntimeGenerator {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let density = f.energy_density();
+		scale_energy(density, f.amount, self.efficiency as u32, 100).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.efficiency
+	}
+}
+
+/// `density * amount * num / den`, computed without intermediate overflow. Like efficiencies, the
+/// result saturates, at `BTU::MAX`.
+fn scale_energy(density: BTU, amount: u32, num: u32, den: u32) -> BTU {
+	let energy = density as u128 * amount as u128 * num as u128 / den as u128;
+	energy.min(BTU::MAX as u128) as BTU
+}
+
+/// Like [`OmniGenerator`], but with a finer grained efficiency of `NUM / DEN`, e.g. a 99.5%
+/// efficient generator is `PreciseGenerator<995, 1000>`. `NUM` must not exceed `DEN`, which is
+/// checked at compile time.
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let pg = PreciseGenerator::<3, 2>;
+/// pg.provide_energy(FuelContainer::<Diesel>::new(10));
+/// # }
+/// ```
+pub struct PreciseGenerator<const NUM: u32, const DEN: u32>;
+
+impl<const NUM: u32, const DEN: u32> PreciseGenerator<NUM, DEN> {
+	/// Referenced in `provide_energy`, such that an invalid ratio fails to compile.
+	const VALID_EFFICIENCY: () = assert!(NUM <= DEN && DEN > 0, "efficiency must be in 0..=1");
+}
+
+impl<const NUM: u32, const DEN: u32, F: Fuel> ProvideEnergy<F> for PreciseGenerator<NUM, DEN> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let () = Self::VALID_EFFICIENCY;
 
-		energy_provided
+		scale_energy(f.energy_density(), f.amount, NUM, DEN).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		let () = Self::VALID_EFFICIENCY;
+		(NUM as u64 * 100 / DEN as u64) as u8
 	}
 }
 
@@ -212,6 +708,61 @@ impl<F1: Fuel, F2: Fuel> Fuel for Mixed<F1, F2> {
 	}
 }
 
+/// Like [`Mixed`], but reports its energy density in the unit `U` instead of flattening it to
+/// `BTU`. By default, that is the native unit of the first fuel, e.g. a blend of `Diesel` and
+/// `LithiumBattery` is measured in `Joule`.
+pub struct MixedAs<F1: Fuel, F2: Fuel, U = <F1 as Fuel>::Output>(PhantomData<(F1, F2, U)>);
+
+impl<F1: Fuel, F2: Fuel, U: Into<BTU> + From<BTU>> Fuel for MixedAs<F1, F2, U> {
+	type Output = U;
+
+	fn energy_density() -> Self::Output {
+		Mixed::<F1, F2>::energy_density().into()
+	}
+}
+
+/// A list of fuels, expressed as a tuple of fuel types, e.g. `(Diesel, Uranium, LithiumBattery)`.
+pub trait FuelList {
+	/// The energy density of each fuel in the list, in BTU.
+	fn densities() -> Vec<BTU>;
+}
+
+macro_rules! impl_fuel_list {
+	($($fuel:ident),+) => {
+		impl<$($fuel: Fuel),+> FuelList for ($($fuel,)+) {
+			fn densities() -> Vec<BTU> {
+				vec![$($fuel::energy_density().into()),+]
+			}
+		}
+	};
+}
+
+impl_fuel_list!(F1);
+impl_fuel_list!(F1, F2);
+impl_fuel_list!(F1, F2, F3);
+impl_fuel_list!(F1, F2, F3, F4);
+impl_fuel_list!(F1, F2, F3, F4, F5);
+impl_fuel_list!(F1, F2, F3, F4, F5, F6);
+impl_fuel_list!(F1, F2, F3, F4, F5, F6, F7);
+impl_fuel_list!(F1, F2, F3, F4, F5, F6, F7, F8);
+
+/// Like [`Mixed`], but for any number of fuels: `MixedN<(F1, F2, F3, F4)>` is an even mix of four
+/// fuels. The energy density is the average of all of them, in BTU.
+pub struct MixedN<L: FuelList>(PhantomData<L>);
+
+impl<L: FuelList> Fuel for MixedN<L> {
+	type Output = BTU;
+
+	fn energy_density() -> Self::Output {
+		let densities = L::densities();
+		let total: u64 = densities.iter().map(|d| *d as u64).sum();
+		(total / densities.len() as u64) as BTU
+	}
+}
+
+/// An even mix of three fuels.
+pub type Mixed3<F1, F2, F3> = MixedN<(F1, F2, F3)>;
+
 // Now think about how you can make the mixer configurable, such that it would produce a new fuel
 // with an energy density that is more influences by one type than the other.
 //
@@ -222,86 +773,2244 @@ impl<F1: Fuel, F2: Fuel> Fuel for Mixed<F1, F2> {
 // The main trick is to overcome the fact that `fn energy_density` does not take in a `self`, so the
 // coefficients need to be incorporated in some other way (you've already seen examples of that in
 // this file ;)).
-pub struct CustomMixed<const C: u8, F1, F2>(PhantomData<(F1, F2)>);
-impl<const C: u8, F1: Fuel, F2: Fuel> Fuel for CustomMixed<C, F1, F2> {
+/// `C` is the percentage of `F1` in the mix, and must not exceed 100. This is checked at compile
+/// time:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// CustomMixed::<150, Diesel, LithiumBattery>::energy_density();
+/// # }
+/// ```
+///
+/// The density is rounded to the nearest BTU. Use `CustomMixed<C, F1, F2, FloorEach>` for the
+/// older, truncating behaviour.
+pub struct CustomMixed<const C: u8, F1, F2, R = RoundNearest>(PhantomData<(F1, F2, R)>);
+
+impl<const C: u8, F1, F2, R> CustomMixed<C, F1, F2, R> {
+	/// Referenced in `energy_density`, such that any `C` above 100 fails to compile rather than
+	/// underflowing `100 - C`.
+	const VALID_COEFFICIENT: () = assert!(C <= 100, "coefficient must not exceed 100");
+}
+
+impl<const C: u8, F1: Fuel, F2: Fuel, R: MixRounding> Fuel for CustomMixed<C, F1, F2, R> {
 	type Output = BTU;
 
 	fn energy_density() -> Self::Output {
-		let fuel_1 = F1::energy_density().into();
-        let fuel_2 = F2::energy_density().into();
-        let c = C as u32;
+		let () = Self::VALID_COEFFICIENT;
 
-        ((fuel_1 * c) / 100 + (fuel_2 * (100 - c)) / 100) as BTU
+		R::mix(F1::energy_density().into(), F2::energy_density().into(), C)
 	}
 }
 
-// Now, any of our existing energy providers can be used with a mix fuel.
-
-/// A function that returns the energy produced by the `OmniGenerator` with efficiency of 80%, when
-/// the fuel type is an even a mix of `Diesel` as `LithiumBattery`;
-pub fn omni_80_energy(amount: u32) -> BTU {
-	let fuel_container: FuelContainer<Mixed::<Diesel, LithiumBattery>> = FuelContainer { amount, _marker: PhantomData };
-	let omni_generator: OmniGenerator<80> = OmniGenerator::<80>;
-	
-	omni_generator.provide_energy(fuel_container).into()
+/// Decides how the weighted density of a [`CustomMixed`] is rounded.
+pub trait MixRounding {
+	/// The density of a mix that is `percent` percent `density_1`, the rest being `density_2`.
+	fn mix(density_1: BTU, density_2: BTU, percent: u8) -> BTU;
 }
 
-// Finally, let's consider marker traits, and some trait bounds.
-
-/// Some traits are just markers. They don't bring any additional functionality anything, other than
-/// marking a type with some trait.
-pub trait IsRenewable {}
-impl IsRenewable for LithiumBattery {}
-
-/// Define the following struct such that it only provides energy if the fuel is `IsRenewable`.
-///
-/// It has perfect efficiency.
-pub struct GreenEngine<F: Fuel>(pub PhantomData<F>);
-impl<F: Fuel> ProvideEnergy<F> for GreenEngine<F> {
-	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
-		let density = F::energy_density().into();
-	    (density * f.amount).into()
+/// Divides once, rounding to the nearest BTU.
+pub struct RoundNearest;
+impl MixRounding for RoundNearest {
+	fn mix(density_1: BTU, density_2: BTU, percent: u8) -> BTU {
+		let c = percent as u64;
+		((density_1 as u64 * c + density_2 as u64 * (100 - c) + 50) / 100) as BTU
 	}
 }
 
-/// Define the following struct such that it only provides energy if the fuel's output type is
-/// `BTU`.
-///
-/// It has perfect efficiency.
-pub struct BritishEngine<F: Fuel>(pub PhantomData<F>);
-impl<F: Fuel<Output = BTU>> ProvideEnergy<F> for BritishEngine<F> {
-	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
-		let density = F::energy_density();
-        density * f.amount
+/// Truncates each term separately, losing up to 2 BTU. This is how mixes used to be computed.
+pub struct FloorEach;
+impl MixRounding for FloorEach {
+	fn mix(density_1: BTU, density_2: BTU, percent: u8) -> BTU {
+		let c = percent as u32;
+		(density_1 * c) / 100 + (density_2 * (100 - c)) / 100
 	}
 }
 
-// Congratulations! you have finished the advance trait section.
-//
-// Disclaimer: the types and traits that you are asked to implement in this module are by no means
-// designed to be sensible. Instead, they are chosen to represent a typical, often difficult,
-// pattern. Some are intentionally slightly convoluted to challenge you :). I am sure if we actually
-// wanted to design a fuel system, we would do better.
+/// Like [`CustomMixed`], but the share of `F1` is the ratio `NUM / DEN`, such that blends like
+/// one third to two thirds can be expressed exactly. The density is rounded to the nearest BTU.
+///
+/// `NUM` must not exceed `DEN`, which is checked at compile time:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// RatioMixed::<4, 3, Diesel, LithiumBattery>::energy_density();
+/// # }
+/// ```
+pub struct RatioMixed<const NUM: u32, const DEN: u32, F1, F2>(PhantomData<(F1, F2)>);
 
-/// This function is not graded. It is just for collecting feedback.
-/// On a scale from 0 - 255, with zero being extremely easy and 255 being extremely hard,
-/// how hard did you find this section of the exam.
-pub fn how_hard_was_this_section() -> u8 {
-	todo!()
+impl<const NUM: u32, const DEN: u32, F1, F2> RatioMixed<NUM, DEN, F1, F2> {
+	/// Referenced in `energy_density`, such that an invalid ratio fails to compile.
+	const VALID_RATIO: () = assert!(NUM <= DEN && DEN > 0, "ratio must be in 0..=1");
 }
 
-/// This function is not graded. It is just for collecting feedback.
-/// How much time (in hours) did you spend on this section of the exam?
-pub fn how_many_hours_did_you_spend_on_this_section() -> u8 {
-	todo!()
-}
+impl<const NUM: u32, const DEN: u32, F1: Fuel, F2: Fuel> Fuel for RatioMixed<NUM, DEN, F1, F2> {
+	type Output = BTU;
 
-#[cfg(test)]
-mod tests {
-	use super::*;
+	fn energy_density() -> Self::Output {
+		let () = Self::VALID_RATIO;
 
-	trait ToBTU {
-		fn to_btu(self) -> BTU;
+		let density_1: BTU = F1::energy_density().into();
+		let density_2: BTU = F2::energy_density().into();
+		let (num, den) = (NUM as u64, DEN as u64);
+
+		((density_1 as u64 * num + density_2 as u64 * (den - num) + den / 2) / den) as BTU
+	}
+}
+
+/// Describes what a mixed fuel is made of, e.g. for reports.
+pub trait MixtureInfo {
+	/// The name of each component fuel and its share of the mix, in percent.
+	fn components() -> Vec<(&'static str, u8)>;
+}
+
+impl<F1: Fuel, F2: Fuel> MixtureInfo for Mixed<F1, F2> {
+	fn components() -> Vec<(&'static str, u8)> {
+		vec![(F1::name(), 50), (F2::name(), 50)]
+	}
+}
+
+impl<const C: u8, F1: Fuel, F2: Fuel, R> MixtureInfo for CustomMixed<C, F1, F2, R> {
+	fn components() -> Vec<(&'static str, u8)> {
+		let () = Self::VALID_COEFFICIENT;
+		vec![(F1::name(), C), (F2::name(), 100 - C)]
+	}
+}
+
+impl<const NUM: u32, const DEN: u32, F1: Fuel, F2: Fuel> MixtureInfo
+	for RatioMixed<NUM, DEN, F1, F2>
+{
+	/// Shares are rounded to the nearest percent, such that they still add up to 100.
+	fn components() -> Vec<(&'static str, u8)> {
+		let () = Self::VALID_RATIO;
+		let share = ((NUM as u64 * 100 + DEN as u64 / 2) / DEN as u64) as u8;
+		vec![(F1::name(), share), (F2::name(), 100 - share)]
+	}
+}
+
+/// The type of a blend of any number of fuels, each with its weight in percent, e.g.
+/// `mix_fuels!(Diesel => 50, Ethanol => 30, Hydrogen => 20)`. The weights must add up to 100,
+/// which is checked at compile time.
+///
+/// The blend is made of nested [`RatioMixed`], such that no precision is lost in the coefficients
+/// of the inner mixes.
+///
+/// ```
+/// use pba_qualifier_exam::h_advanced_traits::*;
+/// use pba_qualifier_exam::mix_fuels;
+///
+/// # fn main() {
+/// type E85 = mix_fuels!(Ethanol => 85, Diesel => 15);
+/// assert_eq!(E85::energy_density(), (80 * 85 + 100 * 15) / 100);
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+/// use pba_qualifier_exam::mix_fuels;
+///
+/// # fn main() {
+/// <mix_fuels!(Diesel => 50, Ethanol => 30)>::energy_density();
+/// # }
+/// ```
+///
+/// A blend of a single fuel is that fuel, so it is rejected with a hint to use it directly.
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+/// use pba_qualifier_exam::mix_fuels;
+///
+/// # fn main() {
+/// <mix_fuels!(Diesel => 100)>::energy_density();
+/// # }
+/// ```
+#[macro_export]
+macro_rules! mix_fuels {
+	($fuel:ty => $weight:expr $(,)?) => {
+		compile_error!(concat!(
+			"a blend needs at least two fuels, use `",
+			stringify!($fuel),
+			"` directly"
+		))
+	};
+	($fuel:ty => $weight:expr, $($rest:ty => $rest_weight:expr),+ $(,)?) => {
+		$crate::h_advanced_traits::RatioMixed<
+			{ $weight },
+			{ $crate::h_advanced_traits::percentages($weight $(+ $rest_weight)+) },
+			$fuel,
+			$crate::mix_fuels!(@inner $($rest => $rest_weight),+),
+		>
+	};
+	(@inner $fuel:ty => $weight:expr) => {
+		$fuel
+	};
+	(@inner $fuel:ty => $weight:expr, $($rest:ty => $rest_weight:expr),+) => {
+		$crate::h_advanced_traits::RatioMixed<
+			{ $weight },
+			{ $weight $(+ $rest_weight)+ },
+			$fuel,
+			$crate::mix_fuels!(@inner $($rest => $rest_weight),+),
+		>
+	};
+}
+
+/// Used by [`mix_fuels!`] to check that the weights add up to 100 at compile time.
+#[doc(hidden)]
+pub const fn percentages(total: u32) -> u32 {
+	assert!(total == 100, "the weights of a blend must add up to 100");
+	total
+}
+
+/// Half `A`, half `B`. Mixes nest, since a mix is a fuel in its own right. For example, a three
+/// level blend burnt by an 80% efficient generator:
+///
+/// ```
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// type Biofuel = EvenBlend<Diesel, Ethanol>;
+/// type Enriched = CustomMixed<70, Biofuel, Hydrogen>;
+/// type Premium = EvenBlend<Enriched, Uranium>;
+///
+/// // 90 BTU, then 0.7 * 90 + 0.3 * 300 = 153 BTU, then (153 + 1000) / 2 = 576.5 BTU.
+/// assert_eq!(Biofuel::energy_density(), 90);
+/// assert_eq!(Enriched::energy_density(), 153);
+/// assert_eq!(Premium::energy_density(), 577);
+///
+/// let energy = OmniGenerator::<80>.provide_energy(FuelContainer::<Premium>::new(10));
+/// assert_eq!(energy, 577 * 10 * 80 / 100);
+/// # }
+/// ```
+pub type EvenBlend<A, B> = CustomMixed<50, A, B>;
+
+/// The empty list of a [`Blend`].
+pub struct Nil;
+
+/// A list of a [`Blend`]: the fuel `F` with a weight of `W`, followed by `Tail`.
+pub struct Cons<F, const W: u32, Tail>(PhantomData<(F, Tail)>);
+
+/// A type-level list of weighted fuels.
+pub trait BlendList {
+	/// The sum of all weights in the list.
+	const TOTAL_WEIGHT: u32;
+
+	/// The sum of the density of each fuel, in BTU, times its weight.
+	fn weighted_density() -> u64;
+}
+
+impl BlendList for Nil {
+	const TOTAL_WEIGHT: u32 = 0;
+
+	fn weighted_density() -> u64 {
+		0
+	}
+}
+
+impl<F: Fuel, const W: u32, Tail: BlendList> BlendList for Cons<F, W, Tail> {
+	const TOTAL_WEIGHT: u32 = W + Tail::TOTAL_WEIGHT;
+
+	fn weighted_density() -> u64 {
+		let density: BTU = F::energy_density().into();
+		density as u64 * W as u64 + Tail::weighted_density()
+	}
+}
+
+/// Builds a blend one fuel at a time, without nesting mixers by hand. Weights are relative, so
+/// they need not add up to 100, but they must not all be zero.
+///
+/// Only the type of the built blend matters, the value is merely a way to spell it:
+///
+/// ```
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let fuel = Blend::new().with::<Diesel, 2>().with::<Ethanol, 1>().with::<Hydrogen, 1>();
+/// let energy = OmniGenerator::<100>.provide_energy(fuel.container(10));
+/// assert_eq!(energy, (100 * 2 + 80 + 300) / 4 * 10);
+/// # }
+/// ```
+pub struct Blend<L = Nil>(PhantomData<L>);
+
+impl Blend {
+	pub fn new() -> Self {
+		Blend(PhantomData)
+	}
+}
+
+impl Default for Blend {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<L: BlendList> Blend<L> {
+	/// Referenced in `energy_density`, such that an empty blend fails to compile.
+	const NOT_EMPTY: () = assert!(L::TOTAL_WEIGHT > 0, "a blend needs at least one weight");
+
+	/// Add `W` parts of `F` to the blend.
+	pub fn with<F: Fuel, const W: u32>(self) -> Blend<Cons<F, W, L>> {
+		Blend(PhantomData)
+	}
+
+	/// A container of `amount` units of this blend.
+	pub fn container(self, amount: u32) -> FuelContainer<Self> {
+		FuelContainer::new(amount)
+	}
+}
+
+impl<L: BlendList> Fuel for Blend<L> {
+	type Output = BTU;
+
+	fn energy_density() -> Self::Output {
+		let () = Self::NOT_EMPTY;
+
+		let total = L::TOTAL_WEIGHT as u64;
+		((L::weighted_density() + total / 2) / total) as BTU
+	}
+}
+
+/// The runtime counterpart of [`CustomMixed`], for when the coefficient comes from configuration
+/// rather than the type system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeMixer {
+	density_1: BTU,
+	density_2: BTU,
+	coefficient: u8,
+}
+
+/// Why a [`RuntimeMixer`] could not be made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixError {
+	/// The coefficient, a percentage, is above 100.
+	CoefficientTooLarge(u8),
+}
+
+impl std::fmt::Display for MixError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			MixError::CoefficientTooLarge(coefficient) => {
+				write!(f, "coefficient must be at most 100, not {coefficient}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for MixError {}
+
+impl RuntimeMixer {
+	/// Mix two fuels of the given densities, `coefficient` percent of the first one. Fails if the
+	/// coefficient is above 100.
+	pub fn new(density_1: BTU, density_2: BTU, coefficient: u8) -> Result<Self, MixError> {
+		if coefficient > 100 {
+			return Err(MixError::CoefficientTooLarge(coefficient));
+		}
+		Ok(Self {
+			density_1,
+			density_2,
+			coefficient,
+		})
+	}
+
+	/// Same as [`RuntimeMixer::new`], taking the densities from two fuel types.
+	pub fn of<F1: Fuel, F2: Fuel>(coefficient: u8) -> Result<Self, MixError> {
+		Self::new(
+			F1::energy_density().into(),
+			F2::energy_density().into(),
+			coefficient,
+		)
+	}
+
+	pub fn coefficient(&self) -> u8 {
+		self.coefficient
+	}
+
+	/// The density of the mix, rounded the same way as [`CustomMixed`].
+	pub fn density_btu(&self) -> BTU {
+		RoundNearest::mix(self.density_1, self.density_2, self.coefficient)
+	}
+
+	/// Burn `amount` units of this mix in `provider`.
+	pub fn provide_with(&self, provider: &dyn DynProvideEnergy, amount: u32) -> BTU {
+		provider.provide_btu(amount, self.density_btu())
+	}
+}
+
+// Now, any of our existing energy providers can be used with a mix fuel.
+
+/// A function that returns the energy produced by the `OmniGenerator` with efficiency of 80%, when
+/// the fuel type is an even a mix of `Diesel` as `LithiumBattery`;
+pub fn omni_80_energy(amount: u32) -> BTU {
+	let fuel_container: FuelContainer<Mixed::<Diesel, LithiumBattery>> = FuelContainer { amount, density: None, _marker: PhantomData };
+	let omni_generator: OmniGenerator<80> = OmniGenerator::<80>;
+	
+	omni_generator.provide_energy(fuel_container).into()
+}
+
+// Finally, let's consider marker traits, and some trait bounds.
+
+/// Some traits are just markers. They don't bring any additional functionality anything, other than
+/// marking a type with some trait.
+pub trait IsRenewable {}
+impl IsRenewable for LithiumBattery {}
+
+/// Marks fuels that are burnt to release their energy.
+pub trait Combustible {}
+impl Combustible for Diesel {}
+impl Combustible for Ethanol {}
+impl Combustible for Hydrogen {}
+impl Combustible for Coal {}
+impl Combustible for NaturalGas {}
+impl Combustible for Gasoline {}
+
+/// A mix is renewable only if both of its components are. Mixing in any fossil fuel is enough to
+/// lose the marker:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// fn assert_renewable<F: IsRenewable>() {}
+///
+/// # fn main() {
+/// assert_renewable::<CustomMixed<90, LithiumBattery, Diesel>>();
+/// # }
+/// ```
+impl<const C: u8, F1: IsRenewable, F2: IsRenewable, R> IsRenewable for CustomMixed<C, F1, F2, R> {}
+
+/// A mix is combustible only if both of its components are:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// fn assert_combustible<F: Combustible>() {}
+///
+/// # fn main() {
+/// assert_combustible::<CustomMixed<50, Diesel, LithiumBattery>>();
+/// # }
+/// ```
+impl<const C: u8, F1: Combustible, F2: Combustible, R> Combustible for CustomMixed<C, F1, F2, R> {}
+
+/// Define the following struct such that it only provides energy if the fuel is `IsRenewable`.
+///
+/// It has perfect efficiency.
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+/// use std::marker::PhantomData;
+///
+/// # fn main() {
+/// GreenEngine::<Diesel>(PhantomData).provide_energy(FuelContainer::new(10));
+/// # }
+/// ```
+pub struct GreenEngine<F: Fuel>(pub PhantomData<F>);
+impl<F: Fuel + IsRenewable> ProvideEnergy<F> for GreenEngine<F> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let density = F::energy_density().into();
+	    (density * f.amount).into()
+	}
+}
+
+/// How renewable a fuel is, on a scale from 0 (fossil) to 100 (fully renewable).
+pub trait RenewabilityScore: Fuel {
+	const SCORE: u8;
+
+	fn score() -> u8 {
+		Self::SCORE
+	}
+}
+
+/// The score from which a fuel may be marked [`IsRenewable`].
+///
+/// Trait impls cannot depend on the value of a constant on stable Rust, so this does not derive
+/// `IsRenewable` by itself; rather, every renewable fuel is expected to clear it.
+pub const RENEWABLE_THRESHOLD: u8 = 60;
+
+impl RenewabilityScore for Diesel {
+	const SCORE: u8 = 0;
+}
+impl RenewabilityScore for Coal {
+	const SCORE: u8 = 0;
+}
+impl RenewabilityScore for NaturalGas {
+	const SCORE: u8 = 0;
+}
+impl RenewabilityScore for Gasoline {
+	const SCORE: u8 = 0;
+}
+impl RenewabilityScore for Uranium {
+	const SCORE: u8 = 30;
+}
+impl RenewabilityScore for Hydrogen {
+	const SCORE: u8 = 50;
+}
+impl RenewabilityScore for Electricity {
+	const SCORE: u8 = 50;
+}
+impl RenewabilityScore for LithiumBattery {
+	const SCORE: u8 = 70;
+}
+
+impl<F1: RenewabilityScore, F2: RenewabilityScore> RenewabilityScore for Mixed<F1, F2> {
+	const SCORE: u8 = ((F1::SCORE as u32 + F2::SCORE as u32 + 1) / 2) as u8;
+}
+
+impl<const C: u8, F1, F2, R> RenewabilityScore for CustomMixed<C, F1, F2, R>
+where
+	F1: RenewabilityScore,
+	F2: RenewabilityScore,
+	R: MixRounding,
+{
+	const SCORE: u8 = {
+		let c = C as u32;
+		((F1::SCORE as u32 * c + F2::SCORE as u32 * (100 - c) + 50) / 100) as u8
+	};
+}
+
+/// Like [`GreenEngine`], but accepts any fuel whose [`RenewabilityScore`] is at least
+/// `MIN_SCORE`, which is checked at compile time:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+/// use std::marker::PhantomData;
+///
+/// # fn main() {
+/// let engine = ThresholdGreenEngine::<Hydrogen, 80>(PhantomData);
+/// engine.provide_energy(FuelContainer::new(10));
+/// # }
+/// ```
+pub struct ThresholdGreenEngine<F: Fuel, const MIN_SCORE: u8 = RENEWABLE_THRESHOLD>(
+	pub PhantomData<F>,
+);
+
+impl<F: RenewabilityScore, const MIN_SCORE: u8> ThresholdGreenEngine<F, MIN_SCORE> {
+	/// Referenced in `provide_energy`, such that a fuel below the bar fails to compile.
+	const CLEARS_BAR: () = assert!(F::SCORE >= MIN_SCORE, "fuel is not renewable enough");
+}
+
+impl<F: RenewabilityScore, const MIN_SCORE: u8> ProvideEnergy<F>
+	for ThresholdGreenEngine<F, MIN_SCORE>
+{
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let () = Self::CLEARS_BAR;
+
+		let density: BTU = F::energy_density().into();
+		(density * f.amount).into()
+	}
+}
+
+/// How much CO2 burning a fuel emits, in grams per thousand BTU of energy released.
+pub trait CarbonIntensity: Fuel {
+	const GRAMS_PER_KBTU: u32;
+
+	fn grams_per_kbtu() -> u32 {
+		Self::GRAMS_PER_KBTU
+	}
+}
+
+impl CarbonIntensity for Diesel {
+	const GRAMS_PER_KBTU: u32 = 74;
+}
+impl CarbonIntensity for Coal {
+	const GRAMS_PER_KBTU: u32 = 95;
+}
+impl CarbonIntensity for NaturalGas {
+	const GRAMS_PER_KBTU: u32 = 53;
+}
+impl CarbonIntensity for Gasoline {
+	const GRAMS_PER_KBTU: u32 = 71;
+}
+impl CarbonIntensity for Uranium {
+	const GRAMS_PER_KBTU: u32 = 4;
+}
+impl CarbonIntensity for Hydrogen {
+	const GRAMS_PER_KBTU: u32 = 40;
+}
+impl CarbonIntensity for Electricity {
+	const GRAMS_PER_KBTU: u32 = 120;
+}
+impl CarbonIntensity for Ethanol {
+	const GRAMS_PER_KBTU: u32 = 0;
+}
+impl CarbonIntensity for LithiumBattery {
+	const GRAMS_PER_KBTU: u32 = 0;
+}
+
+impl<F1: CarbonIntensity, F2: CarbonIntensity> CarbonIntensity for Mixed<F1, F2> {
+	const GRAMS_PER_KBTU: u32 = (F1::GRAMS_PER_KBTU + F2::GRAMS_PER_KBTU + 1) / 2;
+}
+
+impl<const C: u8, F1, F2, R> CarbonIntensity for CustomMixed<C, F1, F2, R>
+where
+	F1: CarbonIntensity,
+	F2: CarbonIntensity,
+	R: MixRounding,
+{
+	const GRAMS_PER_KBTU: u32 = {
+		let c = C as u32;
+		(F1::GRAMS_PER_KBTU * c + F2::GRAMS_PER_KBTU * (100 - c) + 50) / 100
+	};
+}
+
+/// Marks fuels that emit no CO2 on balance, which must have a [`CarbonIntensity`] of zero.
+pub trait CarbonNeutral: CarbonIntensity {}
+impl CarbonNeutral for Ethanol {}
+impl CarbonNeutral for LithiumBattery {}
+impl<const C: u8, F1: CarbonNeutral, F2: CarbonNeutral, R: MixRounding> CarbonNeutral
+	for CustomMixed<C, F1, F2, R>
+{
+}
+
+/// A ledger of CO2 emissions, in grams, and of the offsets bought against them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Offsets {
+	debits: Vec<(&'static str, u64)>,
+	offset: u64,
+}
+
+impl Offsets {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Record the emissions of releasing `energy` BTU out of `F`. Carbon neutral fuels leave no
+	/// entry.
+	pub fn record<F: CarbonIntensity>(&mut self, energy: BTU) {
+		let intensity = F::grams_per_kbtu() as u64;
+		if intensity > 0 {
+			self.debits
+				.push((F::name(), energy as u64 * intensity / 1000));
+		}
+	}
+
+	/// Offset `grams` of CO2, e.g. by planting trees.
+	pub fn offset(&mut self, grams: u64) {
+		self.offset += grams;
+	}
+
+	/// Every recorded emission, in order, with the name of the fuel that caused it.
+	pub fn debits(&self) -> &[(&'static str, u64)] {
+		&self.debits
+	}
+
+	/// The total emissions, before offsets.
+	pub fn gross_emissions(&self) -> u64 {
+		self.debits.iter().map(|(_, grams)| grams).sum()
+	}
+
+	/// The emissions left after offsets. Negative if more was offset than emitted.
+	pub fn net_emissions(&self) -> i64 {
+		self.gross_emissions() as i64 - self.offset as i64
+	}
+}
+
+/// Wraps a provider, and records the emissions of everything it burns in an [`Offsets`] ledger.
+pub struct Accounted<P> {
+	provider: P,
+	offsets: std::cell::RefCell<Offsets>,
+}
+
+impl<P> Accounted<P> {
+	pub fn new(provider: P) -> Self {
+		Self {
+			provider,
+			offsets: Default::default(),
+		}
+	}
+
+	/// The ledger of this provider so far.
+	pub fn offsets(&self) -> std::cell::Ref<'_, Offsets> {
+		self.offsets.borrow()
+	}
+
+	/// Offset `grams` of CO2 in the ledger of this provider.
+	pub fn offset(&self, grams: u64) {
+		self.offsets.borrow_mut().offset(grams);
+	}
+}
+
+impl<F: CarbonIntensity, P: ProvideEnergy<F>> ProvideEnergy<F> for Accounted<P> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		let output: BTU = self.provider.try_provide_energy(f)?.into();
+		self.offsets.borrow_mut().record::<F>(output);
+		Ok(output.into())
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+/// Define the following struct such that it only provides energy if the fuel's output type is
+/// `BTU`.
+///
+/// It has perfect efficiency.
+pub struct BritishEngine<F: Fuel>(pub PhantomData<F>);
+impl<F: Fuel<Output = BTU>> ProvideEnergy<F> for BritishEngine<F> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let density = F::energy_density();
+        density * f.amount
+	}
+}
+
+/// Combines [`GreenEngine`] and [`BritishEngine`]: it only provides energy if the fuel is
+/// renewable *and* its output type is `BTU`, e.g. for audits of renewable, BTU-denominated output.
+///
+/// It has perfect efficiency. A renewable fuel that reports in another unit is rejected:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+/// use std::marker::PhantomData;
+///
+/// # fn main() {
+/// GreenBritishEngine::<LithiumBattery>(PhantomData).provide_energy(FuelContainer::new(10));
+/// # }
+/// ```
+///
+/// So is a BTU-denominated fuel that is not renewable:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+/// use std::marker::PhantomData;
+///
+/// # fn main() {
+/// GreenBritishEngine::<Hydrogen>(PhantomData).provide_energy(FuelContainer::new(10));
+/// # }
+/// ```
+pub struct GreenBritishEngine<F: Fuel>(pub PhantomData<F>);
+impl<F: Fuel<Output = BTU> + IsRenewable> ProvideEnergy<F> for GreenBritishEngine<F> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		F::energy_density() * f.amount
+	}
+}
+
+mod sealed {
+	pub trait Sealed {}
+
+	impl Sealed for super::Diesel {}
+	impl Sealed for super::Coal {}
+	impl Sealed for super::NaturalGas {}
+	impl Sealed for super::Gasoline {}
+	impl Sealed for super::Uranium {}
+}
+
+/// Marks the non-renewable fuels dug out of the ground. This trait is sealed: the list of fossil
+/// fuels is fixed by this module, and cannot be extended by other crates.
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// struct Peat;
+/// impl Fuel for Peat {
+///     type Output = BTU;
+///     fn energy_density() -> BTU {
+///         90
+///     }
+/// }
+/// impl Fossil for Peat {}
+/// # fn main() {}
+/// ```
+pub trait Fossil: sealed::Sealed {}
+impl Fossil for Diesel {}
+impl Fossil for Coal {}
+impl Fossil for NaturalGas {}
+impl Fossil for Gasoline {}
+impl Fossil for Uranium {}
+
+/// An old engine that only runs on [`Fossil`] fuels, at 40% efficiency.
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+/// use std::marker::PhantomData;
+///
+/// # fn main() {
+/// LegacyEngine::<Ethanol>(PhantomData).provide_energy(FuelContainer::new(10));
+/// # }
+/// ```
+pub struct LegacyEngine<F: Fuel + Fossil>(pub PhantomData<F>);
+impl<F: Fuel + Fossil> ProvideEnergy<F> for LegacyEngine<F> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		scale_energy(F::energy_density().into(), f.amount, 40, 100).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		40
+	}
+}
+
+/// A plug-in hybrid style engine that burns two different fuels at once, each with its own
+/// efficiency. Efficiencies saturate at 100%.
+///
+/// Since it consumes two containers per call, it does not implement `ProvideEnergy`.
+pub struct HybridEngine<F1: Fuel, F2: Fuel> {
+	efficiency_1: u8,
+	efficiency_2: u8,
+	_marker: PhantomData<(F1, F2)>,
+}
+
+impl<F1: Fuel, F2: Fuel> HybridEngine<F1, F2> {
+	pub fn new(efficiency_1: u8, efficiency_2: u8) -> Self {
+		Self {
+			efficiency_1: efficiency_1.min(100),
+			efficiency_2: efficiency_2.min(100),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Burn both containers and return the sum of their contributions.
+	pub fn provide_energy_pair(&self, a: FuelContainer<F1>, b: FuelContainer<F2>) -> BTU {
+		let density_1: BTU = F1::energy_density().into();
+		let density_2: BTU = F2::energy_density().into();
+
+		density_1 * a.amount * self.efficiency_1 as u32 / 100
+			+ density_2 * b.amount * self.efficiency_2 as u32 / 100
+	}
+}
+
+/// Gets notified about every provision made by an [`Observed`] provider.
+pub trait EnergyObserver {
+	fn on_provided(&self, fuel_name: &str, amount: u32, output_btu: BTU);
+}
+
+impl<T: Fn(&str, u32, BTU)> EnergyObserver for T {
+	fn on_provided(&self, fuel_name: &str, amount: u32, output_btu: BTU) {
+		self(fuel_name, amount, output_btu)
+	}
+}
+
+/// Wraps a provider and notifies `O` after each provision, e.g. to collect metrics.
+///
+/// Note that the output is passed through BTU, so any remainder smaller than one BTU is lost.
+pub struct Observed<P, O> {
+	provider: P,
+	observer: O,
+}
+
+impl<P, O> Observed<P, O> {
+	pub fn new(provider: P, observer: O) -> Self {
+		Self { provider, observer }
+	}
+}
+
+impl<F: Fuel, P: ProvideEnergy<F>, O: EnergyObserver> ProvideEnergy<F> for Observed<P, O> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		let amount = f.amount;
+		let output = self.provider.try_provide_energy(f).map(Into::<BTU>::into);
+		self.observer
+			.on_provided(F::name(), amount, *output.as_ref().unwrap_or(&0));
+		output.map(Into::into)
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+/// Wraps a provider and keeps track of its lifetime statistics.
+///
+/// As with [`Observed`], the output is passed through BTU.
+pub struct Metered<P> {
+	provider: P,
+	total_provided_btu: core::cell::Cell<u64>,
+	calls: core::cell::Cell<u32>,
+}
+
+impl<P> Metered<P> {
+	pub fn new(provider: P) -> Self {
+		Self {
+			provider,
+			total_provided_btu: Default::default(),
+			calls: Default::default(),
+		}
+	}
+
+	/// The sum of all energy this provider has produced so far.
+	pub fn total_provided_btu(&self) -> u64 {
+		self.total_provided_btu.get()
+	}
+
+	/// The number of times this provider has been used.
+	pub fn calls(&self) -> u32 {
+		self.calls.get()
+	}
+}
+
+impl<F: Fuel, P: ProvideEnergy<F>> ProvideEnergy<F> for Metered<P> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		let output = self.provider.try_provide_energy(f).map(Into::<BTU>::into);
+		self.total_provided_btu
+			.set(self.total_provided_btu.get() + *output.as_ref().unwrap_or(&0) as u64);
+		self.calls.set(self.calls.get() + 1);
+		output.map(Into::into)
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+/// Wraps a provider that needs to warm up: the first `N` provisions lose `penalty` percent of
+/// their output, afterwards the provider runs at its normal efficiency. The penalty saturates at
+/// 100%.
+pub struct WarmUp<P, const N: u32> {
+	provider: P,
+	penalty: u8,
+	provisions: core::cell::Cell<u32>,
+}
+
+impl<P, const N: u32> WarmUp<P, N> {
+	pub fn new(provider: P, penalty: u8) -> Self {
+		Self {
+			provider,
+			penalty: penalty.min(100),
+			provisions: Default::default(),
+		}
+	}
+
+	/// Whether the warm-up period is over.
+	pub fn is_warm(&self) -> bool {
+		self.provisions.get() >= N
+	}
+}
+
+impl<F: Fuel, P: ProvideEnergy<F>, const N: u32> ProvideEnergy<F> for WarmUp<P, N> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		let output = self.provider.try_provide_energy(f).map(Into::<BTU>::into);
+		if self.is_warm() {
+			return output.map(Into::into);
+		}
+
+		self.provisions.set(self.provisions.get() + 1);
+		output.map(|output| (output * (100 - self.penalty as u32) / 100).into())
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		let efficiency = self.provider.current_efficiency();
+		if self.is_warm() {
+			return efficiency;
+		}
+		(efficiency as u32 * (100 - self.penalty as u32) / 100) as u8
+	}
+}
+
+/// Wraps a provider and caps its output to `cap` BTU per provision, e.g. because of the grid
+/// connection limits of a large plant. Anything above the cap is lost, and reported as curtailed.
+pub struct Throttled<P> {
+	provider: P,
+	cap: BTU,
+	curtailed: core::cell::Cell<u64>,
+}
+
+impl<P> Throttled<P> {
+	pub fn new(provider: P, cap: BTU) -> Self {
+		Self {
+			provider,
+			cap,
+			curtailed: Default::default(),
+		}
+	}
+
+	/// The total energy that has been clipped by the cap so far.
+	pub fn curtailed_btu(&self) -> u64 {
+		self.curtailed.get()
+	}
+}
+
+impl<F: Fuel, P: ProvideEnergy<F>> ProvideEnergy<F> for Throttled<P> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		let output: BTU = self.provider.try_provide_energy(f)?.into();
+		let excess = output.saturating_sub(self.cap);
+		self.curtailed.set(self.curtailed.get() + excess as u64);
+		Ok((output - excess).into())
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+/// Wraps a provider that needs to rest after each provision, e.g. a peaker plant. After providing
+/// energy, the next `GAP` calls are refused: they produce nothing and the fuel is lost. Time can
+/// also be advanced without burning fuel, using [`Cooldown::tick`].
+pub struct Cooldown<P, const GAP: u32> {
+	provider: P,
+	remaining: core::cell::Cell<u32>,
+}
+
+impl<P, const GAP: u32> Cooldown<P, GAP> {
+	pub fn new(provider: P) -> Self {
+		Self {
+			provider,
+			remaining: Default::default(),
+		}
+	}
+
+	/// Advance the cooldown by one step.
+	pub fn tick(&self) {
+		self.remaining.set(self.remaining.get().saturating_sub(1));
+	}
+
+	/// Whether the next provision would succeed.
+	pub fn is_ready(&self) -> bool {
+		self.remaining.get() == 0
+	}
+}
+
+impl<F: Fuel, P: ProvideEnergy<F>, const GAP: u32> ProvideEnergy<F> for Cooldown<P, GAP> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		if !self.is_ready() {
+			self.tick();
+			return Err(ProvisionError::CoolingDown);
+		}
+
+		let energy = self.provider.try_provide_energy(f)?;
+		self.remaining.set(GAP);
+		Ok(energy)
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+/// Wraps a provider and makes it fail every now and then, to exercise the resilience logic of its
+/// users. Failures follow a pseudo-random, but fully deterministic, pattern derived from `seed`:
+/// on average `failure_percent` out of 100 provisions fail. A failed provision produces nothing
+/// from `provide_energy`, and [`ProvisionError::Fault`] from `try_provide_energy`.
+#[cfg(feature = "fault-injection")]
+pub struct Flaky<P> {
+	provider: P,
+	failure_percent: u8,
+	state: core::cell::Cell<u32>,
+}
+
+#[cfg(feature = "fault-injection")]
+impl<P> Flaky<P> {
+	pub fn new(provider: P, seed: u32, failure_percent: u8) -> Self {
+		// xorshift gets stuck at zero.
+		let seed = if seed == 0 { 0x9E37_79B9 } else { seed };
+		Self {
+			provider,
+			failure_percent: failure_percent.min(100),
+			state: core::cell::Cell::new(seed),
+		}
+	}
+
+	/// Advance the pseudo-random generator, and return whether the next provision should fail.
+	fn next_fails(&self) -> bool {
+		let mut x = self.state.get();
+		x ^= x << 13;
+		x ^= x >> 17;
+		x ^= x << 5;
+		self.state.set(x);
+		x % 100 < self.failure_percent as u32
+	}
+}
+
+#[cfg(feature = "fault-injection")]
+impl<F: Fuel, P: ProvideEnergy<F>> ProvideEnergy<F> for Flaky<P> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		if self.next_fails() {
+			return Err(ProvisionError::Fault);
+		}
+		self.provider.try_provide_energy(f)
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+/// Wraps a provider and recovers `RECOVERY` percent of the fuel it burns, e.g. regenerative
+/// braking or co-generation. `RECOVERY` saturates at 100%.
+pub struct Regenerative<P, const RECOVERY: u8> {
+	provider: P,
+}
+
+impl<P, const RECOVERY: u8> Regenerative<P, RECOVERY> {
+	pub fn new(provider: P) -> Self {
+		Self { provider }
+	}
+
+	/// Burn all of `f`, and return the created energy along with the recovered fuel.
+	pub fn provide_energy_regenerative<F: Fuel>(
+		&self,
+		f: FuelContainer<F>,
+	) -> (<F as Fuel>::Output, FuelContainer<F>)
+	where
+		P: ProvideEnergy<F>,
+	{
+		let recovered = f.with_amount(scale_energy(1, f.amount, RECOVERY.min(100) as u32, 100));
+		(self.provider.provide_energy(f), recovered)
+	}
+}
+
+/// Using a regenerative provider as a plain provider simply loses the recovered fuel.
+impl<F: Fuel, P: ProvideEnergy<F>, const RECOVERY: u8> ProvideEnergy<F>
+	for Regenerative<P, RECOVERY>
+{
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.provider.provide_energy(f)
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		self.provider.try_provide_energy(f)
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+/// Wraps a provider that has to pay a fixed `overhead` in BTU every time it is started, e.g. for
+/// ignition or spin-up. The output saturates at zero.
+pub struct StartupCost<P> {
+	provider: P,
+	overhead: BTU,
+}
+
+impl<P> StartupCost<P> {
+	pub fn new(provider: P, overhead: BTU) -> Self {
+		Self { provider, overhead }
+	}
+}
+
+impl<F: Fuel, P: ProvideEnergy<F>> ProvideEnergy<F> for StartupCost<P> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		let output: BTU = self.provider.try_provide_energy(f)?.into();
+		Ok(output.saturating_sub(self.overhead).into())
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.provider.current_efficiency()
+	}
+}
+
+// Real engines are less efficient at very low or very high load. The efficiency of the following
+// engine depends on the amount of fuel burned in a single provision.
+
+/// Maps the amount of fuel burned in one provision to an efficiency percent.
+pub trait EfficiencyCurve {
+	fn efficiency_for(amount: u32) -> u8;
+}
+
+/// The same efficiency `E` regardless of load.
+pub struct Flat<const E: u8>;
+impl<const E: u8> EfficiencyCurve for Flat<E> {
+	fn efficiency_for(_amount: u32) -> u8 {
+		E
+	}
+}
+
+/// Efficiency goes linearly from `FROM` at no load to `TO` at `FULL_LOAD`, and stays at `TO`
+/// beyond that.
+pub struct LinearRamp<const FROM: u8, const TO: u8, const FULL_LOAD: u32>;
+impl<const FROM: u8, const TO: u8, const FULL_LOAD: u32> EfficiencyCurve
+	for LinearRamp<FROM, TO, FULL_LOAD>
+{
+	fn efficiency_for(amount: u32) -> u8 {
+		if amount >= FULL_LOAD {
+			return TO;
+		}
+		let delta = (TO as i64 - FROM as i64) * amount as i64 / FULL_LOAD as i64;
+		(FROM as i64 + delta) as u8
+	}
+}
+
+/// Efficiency peaks at `PEAK` when burning exactly `OPTIMAL` units, and falls linearly to `MIN`
+/// at no load and at twice the optimal load (and beyond).
+pub struct Bathtub<const MIN: u8, const PEAK: u8, const OPTIMAL: u32>;
+impl<const MIN: u8, const PEAK: u8, const OPTIMAL: u32> EfficiencyCurve
+	for Bathtub<MIN, PEAK, OPTIMAL>
+{
+	fn efficiency_for(amount: u32) -> u8 {
+		let distance = amount.abs_diff(OPTIMAL).min(OPTIMAL) as u64;
+		let drop = PEAK.saturating_sub(MIN) as u64 * distance / (OPTIMAL as u64).max(1);
+		PEAK - drop as u8
+	}
+}
+
+/// An engine consuming `F` whose efficiency follows the curve `C`. Efficiencies saturate at 100%.
+pub struct CurvedEngine<F: Fuel, C: EfficiencyCurve>(pub PhantomData<(F, C)>);
+impl<F: Fuel, C: EfficiencyCurve> ProvideEnergy<F> for CurvedEngine<F, C> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let density = f.energy_density();
+		let e = C::efficiency_for(f.amount).min(100) as u32;
+		scale_energy(density, f.amount, e, 100).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		C::efficiency_for(1).min(100)
+	}
+}
+
+/// An ad-hoc provider defined by a closure, handy for tests and quick experiments.
+///
+/// Its efficiency is measured by running the closure on a probe of [`FnProvider::PROBE`] units of
+/// fuel, so the closure should not have side effects.
+///
+/// ```
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let double = FnProvider::new(|f: FuelContainer<Diesel>| Joule(f.amount() * 2 * 1055));
+/// let energy: BTU = double.provide_energy(FuelContainer::new(10)).into();
+/// assert_eq!(energy, 20);
+/// # }
+/// ```
+pub struct FnProvider<F, C> {
+	provide: C,
+	_marker: PhantomData<F>,
+}
+
+impl<F: Fuel, C: Fn(FuelContainer<F>) -> <F as Fuel>::Output> FnProvider<F, C> {
+	/// The amount of fuel the efficiency of the closure is measured with.
+	pub const PROBE: u32 = 100;
+
+	pub fn new(provide: C) -> Self {
+		Self {
+			provide,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<F: Fuel, C: Fn(FuelContainer<F>) -> <F as Fuel>::Output> ProvideEnergy<F>
+	for FnProvider<F, C>
+{
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		(self.provide)(f)
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		let contained = scale_energy(F::energy_density().into(), Self::PROBE, 1, 1);
+		if contained == 0 {
+			return 0;
+		}
+		let useful: BTU = (self.provide)(FuelContainer::new(Self::PROBE)).into();
+		(useful as u64 * 100 / contained as u64).min(100) as u8
+	}
+}
+
+// Energy often goes through several stages before it is used, e.g. generator -> transmission ->
+// inverter, each of them losing some of it along the way.
+
+/// Energy that has already been extracted from some fuel. A single unit holds exactly one BTU.
+///
+/// This is what the later stages of a [`Chained`] provider consume.
+pub struct Electricity;
+impl Fuel for Electricity {
+	type Output = BTU;
+	fn energy_density() -> Self::Output {
+		1
+	}
+	fn name() -> &'static str {
+		"Electricity"
+	}
+}
+
+/// A provider whose output is fed, as [`Electricity`], into a second provider. Each stage applies
+/// its own efficiency.
+pub struct Chained<A, B> {
+	first: A,
+	second: B,
+}
+
+impl<A, B> Chained<A, B> {
+	pub fn new(first: A, second: B) -> Self {
+		Self { first, second }
+	}
+}
+
+impl<F: Fuel, A: ProvideEnergy<F>, B: ProvideEnergy<Electricity>> ProvideEnergy<F>
+	for Chained<A, B>
+{
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let intermediate: BTU = self.first.provide_energy(f).into();
+		self.second
+			.provide_energy(FuelContainer::<Electricity>::new(intermediate))
+			.into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		let second = ProvideEnergy::<Electricity>::current_efficiency(&self.second);
+		(self.first.current_efficiency() as u32 * second as u32 / 100) as u8
+	}
+}
+
+/// Fluent combinators for energy providers.
+///
+/// This is implemented for the providers that take [`Electricity`], rather than for every
+/// `ProvideEnergy<F>`, because most providers implement `ProvideEnergy` for many fuels and `F`
+/// could not be inferred at the call site. Other providers can be chained with [`Chained::new`].
+pub trait ProvideEnergyExt: Sized {
+	/// Feed the output of `self` into `next`. See [`Chained`].
+	fn chain<B: ProvideEnergy<Electricity>>(self, next: B) -> Chained<Self, B> {
+		Chained::new(self, next)
+	}
+}
+
+impl<P: ProvideEnergy<Electricity>> ProvideEnergyExt for P {}
+
+/// A combined-cycle plant: the waste heat of the first provider, e.g. a gas turbine, is fed as
+/// [`Electricity`] into the second one, e.g. a steam turbine, and both outputs are added up.
+pub struct CombinedCycle<P1, P2> {
+	primary: P1,
+	secondary: P2,
+}
+
+impl<P1, P2> CombinedCycle<P1, P2> {
+	pub fn new(primary: P1, secondary: P2) -> Self {
+		Self { primary, secondary }
+	}
+}
+
+impl<F: Fuel, P1: ProvideEnergy<F>, P2: ProvideEnergy<Electricity>> ProvideEnergy<F>
+	for CombinedCycle<P1, P2>
+{
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let DetailedEnergy { useful, waste_heat } = self.primary.provide_energy_detailed(f);
+		let useful: BTU = useful.into();
+		let recovered = self
+			.secondary
+			.provide_energy(FuelContainer::new(waste_heat));
+		(useful + recovered).into()
+	}
+
+	/// The waste heat of the primary provider is all the energy it does not turn into output.
+	fn current_efficiency(&self) -> u8 {
+		let primary = self.primary.current_efficiency().min(100) as u32;
+		let secondary = ProvideEnergy::<Electricity>::current_efficiency(&self.secondary).min(100);
+		(primary + (100 - primary) * secondary as u32 / 100) as u8
+	}
+}
+
+/// A fuel whose energy density is only known at runtime, and carried by its containers instead,
+/// see [`FuelContainer::with_density`]. Providers consuming it see the actual units of fuel burned,
+/// such that their wear follows the amount of fuel just like with a fuel known at compile time.
+///
+/// The density of the type itself is unknown, and thus zero: providers must read it from the
+/// container, with [`FuelContainer::energy_density`].
+pub struct RuntimeFuel;
+
+impl Fuel for RuntimeFuel {
+	type Output = BTU;
+	fn energy_density() -> Self::Output {
+		0
+	}
+	fn name() -> &'static str {
+		"RuntimeFuel"
+	}
+}
+
+impl FuelContainer<RuntimeFuel> {
+	/// A container of `amount` units of a fuel whose density is `density` BTU per unit.
+	pub fn with_density(amount: u32, density: BTU) -> Self {
+		Self {
+			amount,
+			density: Some(density),
+			_marker: PhantomData,
+		}
+	}
+}
+
+/// An object safe, fuel agnostic flavour of [`ProvideEnergy`]: the fuel is described at runtime
+/// by its energy density. This allows storing providers for different fuels side by side.
+///
+/// Implemented for every provider that can consume [`RuntimeFuel`].
+pub trait DynProvideEnergy {
+	/// Burn `amount` units of a fuel whose density is `fuel_density` BTU per unit.
+	fn provide_btu(&self, amount: u32, fuel_density: BTU) -> BTU;
+
+	/// Same as [`DynProvideEnergy::provide_btu`], but allows the provider to refuse the provision.
+	/// See [`ProvideEnergy::try_provide_energy`].
+	fn try_provide_btu(&self, amount: u32, fuel_density: BTU) -> Result<BTU, ProvisionError>;
+
+	/// See [`ProvideEnergy::current_efficiency`].
+	fn efficiency(&self) -> u8;
+}
+
+impl<P: ProvideEnergy<RuntimeFuel>> DynProvideEnergy for P {
+	fn provide_btu(&self, amount: u32, fuel_density: BTU) -> BTU {
+		self.provide_energy(FuelContainer::with_density(amount, fuel_density))
+	}
+
+	fn try_provide_btu(&self, amount: u32, fuel_density: BTU) -> Result<BTU, ProvisionError> {
+		self.try_provide_energy(FuelContainer::with_density(amount, fuel_density))
+	}
+
+	fn efficiency(&self) -> u8 {
+		self.current_efficiency()
+	}
+}
+
+/// The outcome of [`compare_providers`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct ComparisonReport {
+	/// The output of each provider, in the order they were given.
+	pub outputs: Vec<(String, BTU)>,
+}
+
+impl ComparisonReport {
+	/// All providers, best first. Ties keep their original order.
+	pub fn ranking(&self) -> Vec<(&str, BTU)> {
+		let mut ranking = self
+			.outputs
+			.iter()
+			.map(|(name, output)| (name.as_str(), *output))
+			.collect::<Vec<_>>();
+		ranking.sort_by_key(|&(_, output)| core::cmp::Reverse(output));
+		ranking
+	}
+
+	/// The name of the provider with the highest output, if any.
+	pub fn best(&self) -> Option<&str> {
+		self.ranking().first().map(|(name, _)| *name)
+	}
+}
+
+/// Burn the same `amount` of `F` in each of the named `providers`, and report how they fare.
+pub fn compare_providers<F: Fuel>(
+	providers: Vec<(&str, &dyn DynProvideEnergy)>,
+	amount: u32,
+) -> ComparisonReport {
+	let density: BTU = F::energy_density().into();
+	let outputs = providers
+		.into_iter()
+		.map(|(name, provider)| (name.to_string(), provider.provide_btu(amount, density)))
+		.collect();
+	ComparisonReport { outputs }
+}
+
+/// A closed set of the built-in engines, for heterogeneous fleets that want static dispatch
+/// rather than trait objects.
+///
+/// Const-generic generators are stored as their runtime equivalent, e.g. an `OmniGenerator<80>`
+/// becomes a `RuntimeGenerator` with an efficiency of 80.
+pub enum AnyProvider<const DECAY: u32 = 1> {
+	Nuclear(NuclearReactor),
+	Combustion(InternalCombustion<DECAY>),
+	Generator(RuntimeGenerator),
+}
+
+impl<const DECAY: u32> AnyProvider<DECAY> {
+	/// Burn `amount` units of a fuel whose density is `fuel_density` BTU per unit.
+	pub fn provide_btu(&self, amount: u32, fuel_density: BTU) -> BTU {
+		match self {
+			Self::Nuclear(p) => DynProvideEnergy::provide_btu(p, amount, fuel_density),
+			Self::Combustion(p) => DynProvideEnergy::provide_btu(p, amount, fuel_density),
+			Self::Generator(p) => DynProvideEnergy::provide_btu(p, amount, fuel_density),
+		}
+	}
+}
+
+impl<const DECAY: u32, F: Fuel> ProvideEnergy<F> for AnyProvider<DECAY> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.provide_btu(f.amount, f.energy_density()).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		match self {
+			Self::Nuclear(_) => 99,
+			Self::Combustion(p) => p.efficiency().min(100),
+			Self::Generator(p) => p.efficiency(),
+		}
+	}
+}
+
+impl<const DECAY: u32> From<NuclearReactor> for AnyProvider<DECAY> {
+	fn from(p: NuclearReactor) -> Self {
+		Self::Nuclear(p)
+	}
+}
+
+impl<const DECAY: u32> From<InternalCombustion<DECAY>> for AnyProvider<DECAY> {
+	fn from(p: InternalCombustion<DECAY>) -> Self {
+		Self::Combustion(p)
+	}
+}
+
+impl<const DECAY: u32> From<RuntimeGenerator> for AnyProvider<DECAY> {
+	fn from(p: RuntimeGenerator) -> Self {
+		Self::Generator(p)
+	}
+}
+
+impl<const DECAY: u32, const EFFICIENCY: u8> From<OmniGenerator<EFFICIENCY>>
+	for AnyProvider<DECAY>
+{
+	fn from(_: OmniGenerator<EFFICIENCY>) -> Self {
+		Self::Generator(RuntimeGenerator::new(EFFICIENCY))
+	}
+}
+
+/// A provider for testing code that depends on [`ProvideEnergy`]. It returns a scripted sequence
+/// of outputs, one per provision, and records the amount of fuel it received each time.
+///
+/// Panics if provisioned more times than there are scripted outputs.
+pub struct MockProvider<F> {
+	outputs: std::cell::RefCell<std::collections::VecDeque<BTU>>,
+	received: std::cell::RefCell<Vec<u32>>,
+	efficiency: u8,
+	_marker: PhantomData<F>,
+}
+
+impl<F: Fuel> MockProvider<F> {
+	pub fn new(outputs: impl IntoIterator<Item = BTU>) -> Self {
+		Self {
+			outputs: std::cell::RefCell::new(outputs.into_iter().collect()),
+			received: Default::default(),
+			efficiency: 100,
+			_marker: PhantomData,
+		}
+	}
+
+	/// Report `efficiency` as the current efficiency, rather than 100%. It saturates at 100%.
+	pub fn with_efficiency(mut self, efficiency: u8) -> Self {
+		self.efficiency = efficiency.min(100);
+		self
+	}
+
+	/// The fuel amounts received so far, in order.
+	pub fn received(&self) -> Vec<u32> {
+		self.received.borrow().clone()
+	}
+
+	/// The number of scripted outputs that have not been used yet.
+	pub fn remaining(&self) -> usize {
+		self.outputs.borrow().len()
+	}
+
+	/// Panics unless the fuel amounts received so far are exactly `expected`.
+	pub fn assert_received(&self, expected: &[u32]) {
+		assert_eq!(
+			self.received.borrow().as_slice(),
+			expected,
+			"MockProvider received unexpected fuel amounts"
+		);
+	}
+}
+
+impl<F: Fuel> ProvideEnergy<F> for MockProvider<F> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.received.borrow_mut().push(f.amount);
+		self.outputs
+			.borrow_mut()
+			.pop_front()
+			.expect("MockProvider ran out of scripted outputs")
+			.into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.efficiency
+	}
+}
+
+/// A provider stacked up by a [`GeneratorBuilder`], consuming `F`. Its stages are fed the units of
+/// `F` as [`RuntimeFuel`], such that they wear by the actual amount of fuel burned.
+pub struct Generator<F> {
+	provider: Metered<BoxedStage>,
+	metered: bool,
+	_marker: PhantomData<F>,
+}
+
+impl<F> Generator<F> {
+	/// The lifetime output, if the generator was built with metering.
+	pub fn total_provided_btu(&self) -> Option<u64> {
+		self.metered.then(|| self.provider.total_provided_btu())
+	}
+
+	/// The lifetime number of provisions, if the generator was built with metering.
+	pub fn calls(&self) -> Option<u32> {
+		self.metered.then(|| self.provider.calls())
+	}
+}
+
+impl<F: Fuel> ProvideEnergy<F> for Generator<F> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.provider
+			.provide_btu(f.amount, f.energy_density())
+			.into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		ProvideEnergy::<RuntimeFuel>::current_efficiency(&self.provider)
+	}
+}
+
+type BoxedStage = Box<dyn ProvideEnergy<RuntimeFuel>>;
+type WrapStage = fn(BoxedStage, u8) -> BoxedStage;
+
+/// Fluently configures a generator out of the building blocks in this module, without having to
+/// spell out the nested wrapper types.
+///
+/// ```
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let generator = GeneratorBuilder::new()
+///     .efficiency(85)
+///     .decay::<LinearByFuel<100>>()
+///     .warm_up::<2>(50)
+///     .throttle(10_000)
+///     .metered()
+///     .build::<Diesel>();
+///
+/// let energy: BTU = generator.provide_energy(FuelContainer::new(10)).into();
+/// assert_eq!(energy, 425);
+/// assert_eq!(generator.total_provided_btu(), Some(425));
+/// # }
+/// ```
+pub struct GeneratorBuilder {
+	efficiency: u8,
+	base: fn(u8) -> BoxedStage,
+	warm_up: Option<(WrapStage, u8)>,
+	throttle: Option<BTU>,
+	metered: bool,
+}
+
+impl Default for GeneratorBuilder {
+	fn default() -> Self {
+		Self {
+			efficiency: 100,
+			base: |efficiency| Box::new(RuntimeGenerator::new(efficiency)),
+			warm_up: None,
+			throttle: None,
+			metered: false,
+		}
+	}
+}
+
+impl GeneratorBuilder {
+	/// A perfectly efficient generator that never wears down.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The initial efficiency, saturating at 100%.
+	pub fn efficiency(mut self, efficiency: u8) -> Self {
+		self.efficiency = efficiency;
+		self
+	}
+
+	/// Wear the generator down according to `D`.
+	pub fn decay<D: DecayPolicy + 'static>(mut self) -> Self {
+		self.base = |efficiency| Box::new(InternalCombustion::<1, D>::new(efficiency));
+		self
+	}
+
+	/// Lose `penalty` percent of the output during the first `N` provisions. See [`WarmUp`].
+	pub fn warm_up<const N: u32>(mut self, penalty: u8) -> Self {
+		self.warm_up = Some((
+			|p, penalty| Box::new(WarmUp::<_, N>::new(p, penalty)),
+			penalty,
+		));
+		self
+	}
+
+	/// Cap the output of each provision. See [`Throttled`].
+	pub fn throttle(mut self, cap: BTU) -> Self {
+		self.throttle = Some(cap);
+		self
+	}
+
+	/// Keep track of the lifetime output. See [`Metered`].
+	pub fn metered(mut self) -> Self {
+		self.metered = true;
+		self
+	}
+
+	pub fn build<F: Fuel>(self) -> Generator<F> {
+		let mut provider = (self.base)(self.efficiency.min(100));
+		if let Some((warm_up, penalty)) = self.warm_up {
+			provider = warm_up(provider, penalty);
+		}
+		if let Some(cap) = self.throttle {
+			provider = Box::new(Throttled::new(provider, cap));
+		}
+
+		Generator {
+			provider: Metered::new(provider),
+			metered: self.metered,
+			_marker: PhantomData,
+		}
+	}
+}
+
+/// How a [`GeneratorPool`] picks the provider for the next provision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+	/// Each provider in turn.
+	RoundRobin,
+	/// The provider that has burned the least fuel so far. Ties go to the first one.
+	LeastWorn,
+}
+
+/// A fleet of providers of the same fuel, rotating provisions among them to level their wear.
+/// An empty pool provides nothing.
+pub struct GeneratorPool<F: Fuel> {
+	providers: Vec<Box<dyn ProvideEnergy<F>>>,
+	rotation: Rotation,
+	next: core::cell::Cell<usize>,
+	fuel_burned: std::cell::RefCell<Vec<u64>>,
+}
+
+impl<F: Fuel> GeneratorPool<F> {
+	pub fn new(rotation: Rotation) -> Self {
+		Self {
+			providers: Vec::new(),
+			rotation,
+			next: Default::default(),
+			fuel_burned: Default::default(),
+		}
+	}
+
+	/// Add a provider to the pool.
+	pub fn with(mut self, provider: impl ProvideEnergy<F> + 'static) -> Self {
+		self.providers.push(Box::new(provider));
+		self.fuel_burned.get_mut().push(0);
+		self
+	}
+
+	/// The amount of fuel each provider has burned so far, in the order they were added.
+	pub fn fuel_burned(&self) -> Vec<u64> {
+		self.fuel_burned.borrow().clone()
+	}
+
+	fn pick(&self) -> usize {
+		match self.rotation {
+			Rotation::RoundRobin => {
+				let index = self.next.get();
+				self.next.set((index + 1) % self.providers.len());
+				index
+			}
+			Rotation::LeastWorn => {
+				let fuel_burned = self.fuel_burned.borrow();
+				(0..fuel_burned.len())
+					.min_by_key(|&i| fuel_burned[i])
+					.unwrap_or_default()
+			}
+		}
+	}
+}
+
+impl<F: Fuel> ProvideEnergy<F> for GeneratorPool<F> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		self.try_provide_energy(f).unwrap_or_else(|_| 0.into())
+	}
+
+	fn try_provide_energy(
+		&self,
+		f: FuelContainer<F>,
+	) -> Result<<F as Fuel>::Output, ProvisionError> {
+		if self.providers.is_empty() {
+			return Ok(0.into());
+		}
+
+		let index = self.pick();
+		self.fuel_burned.borrow_mut()[index] += f.amount as u64;
+		self.providers[index].try_provide_energy(f)
+	}
+}
+
+// The "type-state" pattern from `m_builder` also works for engines: the lifecycle of the following
+// engine is tracked in its type, so energy can only be provided by a running engine.
+
+/// The [`Engine`] is switched off.
+pub struct Off;
+/// The [`Engine`] is running, and can provide energy.
+pub struct Running;
+/// The [`Engine`] is being serviced.
+pub struct Maintenance;
+
+/// An engine that loses one point of efficiency per provision, until it gets serviced which
+/// restores it to its rated efficiency. Efficiencies saturate at 100%.
+///
+/// ```
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let engine = Engine::new(90).start();
+/// let energy: BTU = engine.provide_energy(FuelContainer::<Diesel>::new(10)).into();
+/// assert_eq!(energy, 900);
+/// let engine = engine.stop().service().finish();
+/// # }
+/// ```
+///
+/// An engine that is not running can not provide energy:
+///
+/// ```compile_fail
+/// use pba_qualifier_exam::h_advanced_traits::*;
+///
+/// # fn main() {
+/// let engine = Engine::new(90);
+/// engine.provide_energy(FuelContainer::<Diesel>::new(10));
+/// # }
+/// ```
+pub struct Engine<State> {
+	rated_efficiency: u8,
+	efficiency: core::cell::Cell<u8>,
+	_state: PhantomData<State>,
+}
+
+impl<State> Engine<State> {
+	/// The efficiency that will be applied to the next provision.
+	pub fn efficiency(&self) -> u8 {
+		self.efficiency.get()
+	}
+
+	fn transition<Next>(self) -> Engine<Next> {
+		Engine {
+			rated_efficiency: self.rated_efficiency,
+			efficiency: self.efficiency,
+			_state: PhantomData,
+		}
+	}
+}
+
+impl Engine<Off> {
+	pub fn new(efficiency: u8) -> Self {
+		let efficiency = efficiency.min(100);
+		Self {
+			rated_efficiency: efficiency,
+			efficiency: core::cell::Cell::new(efficiency),
+			_state: PhantomData,
+		}
+	}
+
+	pub fn start(self) -> Engine<Running> {
+		self.transition()
+	}
+
+	pub fn service(self) -> Engine<Maintenance> {
+		self.transition()
+	}
+}
+
+impl Engine<Running> {
+	pub fn stop(self) -> Engine<Off> {
+		self.transition()
+	}
+}
+
+impl Engine<Maintenance> {
+	/// Finish the maintenance, restoring the rated efficiency.
+	pub fn finish(self) -> Engine<Off> {
+		self.efficiency.set(self.rated_efficiency);
+		self.transition()
+	}
+}
+
+impl<F: Fuel> ProvideEnergy<F> for Engine<Running> {
+	fn provide_energy(&self, f: FuelContainer<F>) -> <F as Fuel>::Output {
+		let density = f.energy_density();
+		let efficiency = self.efficiency.get();
+		self.efficiency.set(efficiency.saturating_sub(1));
+		scale_energy(density, f.amount, efficiency as u32, 100).into()
+	}
+
+	fn current_efficiency(&self) -> u8 {
+		self.efficiency.get()
+	}
+}
+
+// Long running simulations need to checkpoint their providers and resume them later. Since this
+// crate has no dependencies, checkpoints are plain data that users can persist as they see fit.
+
+/// A provider whose internal state can be captured and later restored.
+pub trait Checkpoint: Sized {
+	/// Everything needed to rebuild the provider, e.g. its current efficiency and counters.
+	type State: Clone + core::fmt::Debug + PartialEq;
+
+	/// Capture the current state.
+	fn checkpoint(&self) -> Self::State;
+
+	/// Rebuild a provider from a previously captured state.
+	fn restore(state: Self::State) -> Self;
+}
+
+impl Checkpoint for NuclearReactor {
+	type State = ();
+
+	fn checkpoint(&self) -> Self::State {}
+
+	fn restore(_: Self::State) -> Self {
+		NuclearReactor
+	}
+}
+
+impl<const EFFICIENCY: u8> Checkpoint for OmniGenerator<EFFICIENCY> {
+	type State = ();
+
+	fn checkpoint(&self) -> Self::State {}
+
+	fn restore(_: Self::State) -> Self {
+		OmniGenerator
+	}
+}
+
+impl Checkpoint for RuntimeGenerator {
+	type State = u8;
+
+	fn checkpoint(&self) -> Self::State {
+		self.efficiency
+	}
+
+	fn restore(state: Self::State) -> Self {
+		Self::new(state)
+	}
+}
+
+/// The state of an [`InternalCombustion`] engine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CombustionState {
+	pub initial_efficiency: u8,
+	pub efficiency: u8,
+	pub calls: u32,
+	pub fuel_burned: u64,
+}
+
+impl<const DECAY: u32, D: DecayPolicy> Checkpoint for InternalCombustion<DECAY, D> {
+	type State = CombustionState;
+
+	fn checkpoint(&self) -> Self::State {
+		CombustionState {
+			initial_efficiency: self.initial_efficiency,
+			efficiency: self.efficiency.get(),
+			calls: self.calls.get(),
+			fuel_burned: self.fuel_burned.get(),
+		}
+	}
+
+	fn restore(state: Self::State) -> Self {
+		Self {
+			initial_efficiency: state.initial_efficiency,
+			efficiency: core::cell::Cell::new(state.efficiency),
+			calls: core::cell::Cell::new(state.calls),
+			fuel_burned: core::cell::Cell::new(state.fuel_burned),
+			_policy: PhantomData,
+		}
+	}
+}
+
+impl<const DECAY: u32> Checkpoint for SyncInternalCombustion<DECAY> {
+	type State = u8;
+
+	fn checkpoint(&self) -> Self::State {
+		self.0.load(core::sync::atomic::Ordering::SeqCst)
+	}
+
+	fn restore(state: Self::State) -> Self {
+		Self::new(state)
+	}
+}
+
+/// The state of a [`Metered`] provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MeteredState<S> {
+	pub provider: S,
+	pub total_provided_btu: u64,
+	pub calls: u32,
+}
+
+impl<P: Checkpoint> Checkpoint for Metered<P> {
+	type State = MeteredState<P::State>;
+
+	fn checkpoint(&self) -> Self::State {
+		MeteredState {
+			provider: self.provider.checkpoint(),
+			total_provided_btu: self.total_provided_btu.get(),
+			calls: self.calls.get(),
+		}
+	}
+
+	fn restore(state: Self::State) -> Self {
+		Self {
+			provider: P::restore(state.provider),
+			total_provided_btu: core::cell::Cell::new(state.total_provided_btu),
+			calls: core::cell::Cell::new(state.calls),
+		}
+	}
+}
+
+/// The state of a [`WarmUp`] provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WarmUpState<S> {
+	pub provider: S,
+	pub penalty: u8,
+	pub provisions: u32,
+}
+
+impl<P: Checkpoint, const N: u32> Checkpoint for WarmUp<P, N> {
+	type State = WarmUpState<P::State>;
+
+	fn checkpoint(&self) -> Self::State {
+		WarmUpState {
+			provider: self.provider.checkpoint(),
+			penalty: self.penalty,
+			provisions: self.provisions.get(),
+		}
+	}
+
+	fn restore(state: Self::State) -> Self {
+		Self {
+			provider: P::restore(state.provider),
+			penalty: state.penalty,
+			provisions: core::cell::Cell::new(state.provisions),
+		}
+	}
+}
+
+/// The state of a [`Throttled`] provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThrottledState<S> {
+	pub provider: S,
+	pub cap: BTU,
+	pub curtailed: u64,
+}
+
+impl<P: Checkpoint> Checkpoint for Throttled<P> {
+	type State = ThrottledState<P::State>;
+
+	fn checkpoint(&self) -> Self::State {
+		ThrottledState {
+			provider: self.provider.checkpoint(),
+			cap: self.cap,
+			curtailed: self.curtailed.get(),
+		}
+	}
+
+	fn restore(state: Self::State) -> Self {
+		Self {
+			provider: P::restore(state.provider),
+			cap: state.cap,
+			curtailed: core::cell::Cell::new(state.curtailed),
+		}
+	}
+}
+
+/// The state of a [`Cooldown`] provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CooldownState<S> {
+	pub provider: S,
+	pub remaining: u32,
+}
+
+impl<P: Checkpoint, const GAP: u32> Checkpoint for Cooldown<P, GAP> {
+	type State = CooldownState<P::State>;
+
+	fn checkpoint(&self) -> Self::State {
+		CooldownState {
+			provider: self.provider.checkpoint(),
+			remaining: self.remaining.get(),
+		}
+	}
+
+	fn restore(state: Self::State) -> Self {
+		Self {
+			provider: P::restore(state.provider),
+			remaining: core::cell::Cell::new(state.remaining),
+		}
+	}
+}
+
+// Some simulations are driven by an async runtime. The following lets any provider be awaited,
+// optionally with some artificial latency, without tying this crate to a particular runtime.
+
+/// A boxed future returned by [`AsyncProvideEnergy`].
+#[cfg(feature = "async")]
+pub type EnergyFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + 'a>>;
+
+/// The async counterpart of [`ProvideEnergy`].
+#[cfg(feature = "async")]
+pub trait AsyncProvideEnergy<F: Fuel> {
+	/// Consume the fuel container and eventually return the created energy.
+	fn provide_energy_async<'a>(
+		&'a self,
+		f: FuelContainer<F>,
+	) -> EnergyFuture<'a, <F as Fuel>::Output>
+	where
+		F: 'a;
+}
+
+/// Adapts any [`ProvideEnergy`] into an [`AsyncProvideEnergy`], waiting for `latency` before
+/// every provision.
+#[cfg(feature = "async")]
+pub struct AsyncAdapter<P> {
+	provider: P,
+	latency: std::time::Duration,
+}
+
+#[cfg(feature = "async")]
+impl<P> AsyncAdapter<P> {
+	/// Wrap `provider` without any latency.
+	pub fn new(provider: P) -> Self {
+		Self::with_latency(provider, std::time::Duration::ZERO)
+	}
+
+	/// Wrap `provider`, waiting for `latency` before each provision completes.
+	pub fn with_latency(provider: P, latency: std::time::Duration) -> Self {
+		Self { provider, latency }
+	}
+}
+
+#[cfg(feature = "async")]
+impl<F: Fuel, P: ProvideEnergy<F>> AsyncProvideEnergy<F> for AsyncAdapter<P> {
+	fn provide_energy_async<'a>(
+		&'a self,
+		f: FuelContainer<F>,
+	) -> EnergyFuture<'a, <F as Fuel>::Output>
+	where
+		F: 'a,
+	{
+		let delay = Delay::new(self.latency);
+		Box::pin(async move {
+			delay.await;
+			self.provider.provide_energy(f)
+		})
+	}
+}
+
+/// A runtime agnostic timer future. The waker of the latest poll is notified from a helper thread
+/// once the deadline has passed.
+#[cfg(feature = "async")]
+struct Delay {
+	deadline: std::time::Instant,
+	/// Shared with the helper thread, which is started on the first poll.
+	waker: Option<std::sync::Arc<std::sync::Mutex<std::task::Waker>>>,
+}
+
+#[cfg(feature = "async")]
+impl Delay {
+	fn new(latency: std::time::Duration) -> Self {
+		Self {
+			deadline: std::time::Instant::now() + latency,
+			waker: None,
+		}
+	}
+}
+
+#[cfg(feature = "async")]
+impl std::future::Future for Delay {
+	type Output = ();
+
+	fn poll(
+		mut self: std::pin::Pin<&mut Self>,
+		cx: &mut std::task::Context<'_>,
+	) -> std::task::Poll<()> {
+		let now = std::time::Instant::now();
+		if now >= self.deadline {
+			return std::task::Poll::Ready(());
+		}
+
+		// The future may have moved to another task since the last poll.
+		if let Some(waker) = &self.waker {
+			let mut waker = waker.lock().unwrap_or_else(|e| e.into_inner());
+			if !waker.will_wake(cx.waker()) {
+				*waker = cx.waker().clone();
+			}
+			return std::task::Poll::Pending;
+		}
+
+		let waker = std::sync::Arc::new(std::sync::Mutex::new(cx.waker().clone()));
+		self.waker = Some(waker.clone());
+		let remaining = self.deadline - now;
+		std::thread::spawn(move || {
+			std::thread::sleep(remaining);
+			let waker = waker.lock().unwrap_or_else(|e| e.into_inner()).clone();
+			waker.wake();
+		});
+
+		std::task::Poll::Pending
+	}
+}
+
+// Congratulations! you have finished the advance trait section.
+//
+// Disclaimer: the types and traits that you are asked to implement in this module are by no means
+// designed to be sensible. Instead, they are chosen to represent a typical, often difficult,
+// pattern. Some are intentionally slightly convoluted to challenge you :). I am sure if we actually
+// wanted to design a fuel system, we would do better.
+
+/// This function is not graded. It is just for collecting feedback.
+/// On a scale from 0 - 255, with zero being extremely easy and 255 being extremely hard,
+/// how hard did you find this section of the exam.
+pub fn how_hard_was_this_section() -> u8 {
+	todo!()
+}
+
+/// This function is not graded. It is just for collecting feedback.
+/// How much time (in hours) did you spend on this section of the exam?
+pub fn how_many_hours_did_you_spend_on_this_section() -> u8 {
+	todo!()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	trait ToBTU {
+		fn to_btu(self) -> BTU;
 	}
 
 	impl<T: Into<BTU>> ToBTU for T {
@@ -311,41 +3020,901 @@ mod tests {
 	}
 
 	#[test]
-	fn nuclear() {
-		let nr = NuclearReactor;
+	fn nuclear() {
+		let nr = NuclearReactor;
+		assert_eq!(
+			nr.provide_energy(FuelContainer::<Uranium>::new(10))
+				.to_btu(),
+			9900
+		);
+		assert_eq!(
+			nr.provide_energy(FuelContainer::<Uranium>::new(10))
+				.to_btu(),
+			9900
+		);
+	}
+
+	#[test]
+	fn ic_1() {
+		let ic = InternalCombustion::<3>::new(120);
+		assert_eq!(
+			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
+			1000
+		);
+		assert_eq!(
+			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
+			1000
+		);
+		assert_eq!(
+			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
+			1000
+		);
+		assert_eq!(
+			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
+			990
+		);
+	}
+
+	#[test]
+	fn integer_math_is_exact_for_large_amounts() {
+		// 2^24 + 1 units would already be rounded by `f32`.
+		let amount = (1 << 24) + 1;
+		assert_eq!(
+			NuclearReactor
+				.provide_energy(FuelContainer::<Electricity>::new(amount))
+				.to_btu(),
+			16_609_444
+		);
+	}
+
+	#[test]
+	fn ic_efficiency_saturates_at_zero() {
+		let ic = InternalCombustion::<3>::new(100);
+		for _ in 0..500 {
+			ic.provide_energy(FuelContainer::<Diesel>::new(10));
+		}
+		assert_eq!(
+			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
+			0
+		);
+	}
+
+	#[test]
+	fn sync_ic_matches_ic() {
+		let ic = InternalCombustion::<3>::new(120);
+		let sync_ic = SyncInternalCombustion::<3>::new(120);
+		for _ in 0..200 {
+			assert_eq!(
+				ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
+				sync_ic
+					.provide_energy(FuelContainer::<Diesel>::new(10))
+					.to_btu()
+			);
+		}
+	}
+
+	#[test]
+	fn sync_ic_shared_across_threads() {
+		fn assert_send_sync<T: Send + Sync>() {}
+		assert_send_sync::<SyncInternalCombustion<3>>();
+
+		let ic = std::sync::Arc::new(SyncInternalCombustion::<3>::new(100));
+		let handles = (0..4)
+			.map(|_| {
+				let ic = ic.clone();
+				std::thread::spawn(move || {
+					for _ in 0..10 {
+						ic.provide_energy(FuelContainer::<Diesel>::new(10));
+					}
+				})
+			})
+			.collect::<Vec<_>>();
+		handles.into_iter().for_each(|h| h.join().unwrap());
+
+		// 40 provisions in total, each decaying the efficiency by one point.
+		assert_eq!(
+			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
+			600
+		);
+	}
+
+	#[test]
+	fn hybrid_sums_both_fuels() {
+		let hybrid = HybridEngine::<Diesel, LithiumBattery>::new(50, 150);
+		assert_eq!(
+			hybrid.provide_energy_pair(
+				FuelContainer::<Diesel>::new(10),
+				FuelContainer::<LithiumBattery>::new(10)
+			),
+			500 + 2000
+		);
+	}
+
+	#[test]
+	fn observed_notifies_observer() {
+		let log = std::cell::RefCell::new(Vec::new());
+		let observer = |fuel: &str, amount: u32, output: BTU| {
+			log.borrow_mut().push((fuel.to_string(), amount, output))
+		};
+		let observed = Observed::new(OmniGenerator::<100>, &observer);
+
+		assert_eq!(
+			observed
+				.provide_energy(FuelContainer::<Diesel>::new(10))
+				.to_btu(),
+			1000
+		);
+		assert_eq!(
+			observed
+				.provide_energy(FuelContainer::<Uranium>::new(2))
+				.to_btu(),
+			2000
+		);
+		assert_eq!(
+			*log.borrow(),
+			vec![
+				("Diesel".to_string(), 10, 1000),
+				("Uranium".to_string(), 2, 2000)
+			]
+		);
+	}
+
+	#[test]
+	fn metered_tracks_lifetime_stats() {
+		let metered = Metered::new(InternalCombustion::<3>::new(100));
+		assert_eq!(metered.calls(), 0);
+		assert_eq!(metered.total_provided_btu(), 0);
+
+		metered.provide_energy(FuelContainer::<Diesel>::new(10));
+		metered.provide_energy(FuelContainer::<Diesel>::new(10));
+
+		assert_eq!(metered.calls(), 2);
+		assert_eq!(metered.total_provided_btu(), 1000 + 990);
+	}
+
+	#[test]
+	fn warm_up_penalizes_first_provisions() {
+		let engine = WarmUp::<_, 2>::new(OmniGenerator::<100>, 30);
+		assert!(!engine.is_warm());
+		let mut outputs = Vec::new();
+		for _ in 0..3 {
+			outputs.push(
+				engine
+					.provide_energy(FuelContainer::<Diesel>::new(10))
+					.to_btu(),
+			);
+		}
+
+		assert!(engine.is_warm());
+		assert_eq!(outputs, vec![700, 700, 1000]);
+	}
+
+	#[test]
+	fn throttled_clips_output() {
+		let throttled = Throttled::new(OmniGenerator::<100>, 1500);
+		assert_eq!(
+			throttled
+				.provide_energy(FuelContainer::<Diesel>::new(10))
+				.to_btu(),
+			1000
+		);
+		assert_eq!(throttled.curtailed_btu(), 0);
+
+		assert_eq!(
+			throttled
+				.provide_energy(FuelContainer::<Uranium>::new(2))
+				.to_btu(),
+			1500
+		);
+		assert_eq!(throttled.curtailed_btu(), 500);
+	}
+
+	#[test]
+	fn cooldown_refuses_frequent_provisions() {
+		let peaker = Cooldown::<_, 2>::new(OmniGenerator::<100>);
+		let provide = || peaker.try_provide_energy(FuelContainer::<Diesel>::new(10));
+
+		assert_eq!(provide().unwrap().to_btu(), 1000);
+		assert_eq!(provide().unwrap_err(), ProvisionError::CoolingDown);
+		assert_eq!(
+			peaker
+				.provide_energy(FuelContainer::<Diesel>::new(10))
+				.to_btu(),
+			0
+		);
+		assert!(peaker.is_ready());
+		assert_eq!(provide().unwrap().to_btu(), 1000);
+
+		peaker.tick();
+		peaker.tick();
+		assert_eq!(provide().unwrap().to_btu(), 1000);
+	}
+
+	#[test]
+	fn cooldown_ignores_failed_provisions() {
+		struct Broken;
+		impl ProvideEnergy<Diesel> for Broken {
+			fn provide_energy(&self, _: FuelContainer<Diesel>) -> Joule {
+				Joule(0)
+			}
+
+			fn try_provide_energy(
+				&self,
+				_: FuelContainer<Diesel>,
+			) -> Result<Joule, ProvisionError> {
+				Err(ProvisionError::Fault)
+			}
+		}
+
+		let peaker = Cooldown::<_, 2>::new(Broken);
+		let provide = || peaker.try_provide_energy(FuelContainer::<Diesel>::new(10));
+		assert_eq!(provide().unwrap_err(), ProvisionError::Fault);
+		assert!(peaker.is_ready());
+		assert_eq!(provide().unwrap_err(), ProvisionError::Fault);
+	}
+
+	#[test]
+	fn wrappers_forward_failures() {
+		let peaker = Cooldown::<_, 1>::new(NuclearReactor);
+		let stack = Metered::new(Accounted::new(WarmUp::<_, 1>::new(
+			Throttled::new(StartupCost::new(Regenerative::<_, 0>::new(peaker), 90), 500),
+			0,
+		)));
+		let provide = || {
+			stack
+				.try_provide_energy(FuelContainer::<Uranium>::new(1))
+				.map(|e| e.to_btu())
+		};
+		assert_eq!(provide(), Ok(500));
+		assert_eq!(provide(), Err(ProvisionError::CoolingDown));
+		assert_eq!(provide(), Ok(500));
+		assert_eq!((stack.calls(), stack.total_provided_btu()), (3, 1000));
+
+		let pool =
+			GeneratorPool::new(Rotation::RoundRobin).with(Cooldown::<_, 1>::new(NuclearReactor));
+		let provide = || {
+			pool.try_provide_energy(FuelContainer::<Uranium>::new(1))
+				.map(|e| e.to_btu())
+		};
+		assert_eq!(provide(), Ok(990));
+		assert_eq!(provide(), Err(ProvisionError::CoolingDown));
+		assert_eq!(
+			pool.provide_energy(FuelContainer::<Uranium>::new(1))
+				.to_btu(),
+			990
+		);
+	}
+
+	#[cfg(feature = "fault-injection")]
+	#[test]
+	fn flaky_is_deterministic() {
+		let pattern = |seed| {
+			let flaky = Flaky::new(OmniGenerator::<100>, seed, 30);
+			(0..100)
+				.map(|_| {
+					flaky
+						.try_provide_energy(FuelContainer::<Diesel>::new(1))
+						.is_ok()
+				})
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(pattern(42), pattern(42));
+		assert_ne!(pattern(42), pattern(7));
+		let failures = pattern(42).iter().filter(|ok| !**ok).count();
+		assert!((10..50).contains(&failures));
+
+		let never = Flaky::new(OmniGenerator::<100>, 1, 0);
+		let always = Flaky::new(OmniGenerator::<100>, 1, 100);
+		for _ in 0..100 {
+			assert_eq!(
+				never
+					.provide_energy(FuelContainer::<Diesel>::new(1))
+					.to_btu(),
+				100
+			);
+			assert_eq!(
+				always
+					.try_provide_energy(FuelContainer::<Diesel>::new(1))
+					.unwrap_err(),
+				ProvisionError::Fault
+			);
+		}
+	}
+
+	#[test]
+	fn regenerative_recovers_fuel() {
+		let regen = Regenerative::<_, 25>::new(OmniGenerator::<100>);
+		let (energy, recovered) =
+			regen.provide_energy_regenerative(FuelContainer::<Diesel>::new(10));
+		assert_eq!(energy.to_btu(), 1000);
+		assert_eq!(recovered.amount(), 2);
+
+		let (energy, recovered) = regen.provide_energy_regenerative(recovered);
+		assert_eq!(energy.to_btu(), 200);
+		assert_eq!(recovered.amount(), 0);
+	}
+
+	#[test]
+	fn startup_cost_is_deducted() {
+		let plant = StartupCost::new(OmniGenerator::<100>, 150);
+		assert_eq!(
+			plant
+				.provide_energy(FuelContainer::<Diesel>::new(10))
+				.to_btu(),
+			850
+		);
+		assert_eq!(
+			plant
+				.provide_energy(FuelContainer::<Diesel>::new(1))
+				.to_btu(),
+			0
+		);
+	}
+
+	#[test]
+	fn efficiency_curves() {
+		assert_eq!(Flat::<70>::efficiency_for(0), 70);
+		assert_eq!(Flat::<70>::efficiency_for(1_000), 70);
+
+		type Ramp = LinearRamp<20, 90, 100>;
+		assert_eq!(Ramp::efficiency_for(0), 20);
+		assert_eq!(Ramp::efficiency_for(50), 55);
+		assert_eq!(Ramp::efficiency_for(100), 90);
+		assert_eq!(Ramp::efficiency_for(500), 90);
+
+		type Tub = Bathtub<40, 90, 100>;
+		assert_eq!(Tub::efficiency_for(0), 40);
+		assert_eq!(Tub::efficiency_for(50), 65);
+		assert_eq!(Tub::efficiency_for(100), 90);
+		assert_eq!(Tub::efficiency_for(150), 65);
+		assert_eq!(Tub::efficiency_for(1_000), 40);
+	}
+
+	#[test]
+	fn curved_engine_follows_curve() {
+		let engine = CurvedEngine::<Diesel, Bathtub<40, 90, 100>>(PhantomData);
+		assert_eq!(
+			engine
+				.provide_energy(FuelContainer::<Diesel>::new(100))
+				.to_btu(),
+			9000
+		);
+		assert_eq!(
+			engine
+				.provide_energy(FuelContainer::<Diesel>::new(200))
+				.to_btu(),
+			8000
+		);
+		let engine = CurvedEngine::<Hydrogen, Bathtub<40, 90, 100>>(PhantomData);
+		assert_eq!(
+			engine.provide_energy(FuelContainer::new(30_000_000)),
+			3_600_000_000
+		);
+	}
+
+	#[test]
+	fn fn_provider_calls_closure() {
+		let half = FnProvider::new(|f: FuelContainer<LithiumBattery>| {
+			OmniGenerator::<50>.provide_energy(f)
+		});
+		assert_eq!(half.provide_energy(FuelContainer::new(10)).to_btu(), 1000);
+	}
+
+	#[test]
+	fn chained_applies_every_stage() {
+		let stack = NuclearReactor
+			.chain(OmniGenerator::<90>)
+			.chain(OmniGenerator::<95>);
+		assert_eq!(
+			stack
+				.provide_energy(FuelContainer::<Uranium>::new(10))
+				.to_btu(),
+			8464
+		);
+
+		// Iterators keep their own `chain`.
+		assert_eq!((0..2u32).chain(5..6).collect::<Vec<_>>(), [0, 1, 5]);
+	}
+
+	#[test]
+	fn combined_cycle_reuses_waste_heat() {
+		let plant = CombinedCycle::new(OmniGenerator::<60>, OmniGenerator::<50>);
+		assert_eq!(
+			plant
+				.provide_energy(FuelContainer::<Diesel>::new(10))
+				.to_btu(),
+			600 + 200
+		);
+	}
+
+	#[test]
+	fn dyn_providers_burn_fuel_units() {
+		let engine = WearByFuel::<10>::new(90);
+		assert_eq!(engine.provide_btu(10, 100), 900);
+		assert_eq!(engine.efficiency(), 89);
+		assert_eq!(engine.checkpoint().fuel_burned, 10);
+
+		let generator = RuntimeGenerator::new(100);
+		assert_eq!(generator.provide_btu(100_000, 100_000), BTU::MAX);
+
+		// The density travels with the containers, even to other threads.
+		let batch = (0..4)
+			.map(|_| FuelContainer::with_density(10, 100))
+			.collect();
+		assert_eq!(
+			RuntimeGenerator::new(50).provide_energy_par_batch(batch),
+			2000
+		);
+	}
+
+	#[test]
+	fn compare_providers_ranks_by_output() {
+		let report = compare_providers::<Diesel>(
+			vec![
+				("omni", &OmniGenerator::<80>),
+				("nuclear", &NuclearReactor),
+				("runtime", &RuntimeGenerator::new(80)),
+			],
+			10,
+		);
+
+		assert_eq!(
+			report.outputs,
+			vec![
+				("omni".to_string(), 800),
+				("nuclear".to_string(), 990),
+				("runtime".to_string(), 800)
+			]
+		);
+		assert_eq!(
+			report.ranking(),
+			vec![("nuclear", 990), ("omni", 800), ("runtime", 800)]
+		);
+		assert_eq!(report.best(), Some("nuclear"));
+		assert_eq!(compare_providers::<Diesel>(vec![], 10).best(), None);
+	}
+
+	#[test]
+	fn any_provider_dispatches_statically() {
+		let fleet: Vec<AnyProvider> = vec![
+			NuclearReactor.into(),
+			InternalCombustion::new(90).into(),
+			OmniGenerator::<80>.into(),
+		];
+		let outputs = fleet
+			.iter()
+			.map(|p| p.provide_btu(10, 100))
+			.collect::<Vec<_>>();
+		assert_eq!(outputs, vec![990, 900, 800]);
+
+		assert_eq!(
+			fleet[1]
+				.provide_energy(FuelContainer::<Diesel>::new(10))
+				.to_btu(),
+			890
+		);
+		match &fleet[1] {
+			AnyProvider::Combustion(engine) => assert_eq!(engine.checkpoint().fuel_burned, 20),
+			_ => unreachable!(),
+		}
+		assert_eq!(fleet[2].provide_btu(100_000, 100_000), BTU::MAX);
+	}
+
+	#[test]
+	fn mock_provider_replays_script() {
+		let mock = MockProvider::<Diesel>::new([10, 20]);
+		let metered = Metered::new(&mock);
+
+		metered.provide_energy(FuelContainer::new(1));
+		metered.provide_energy(FuelContainer::new(2));
+
+		assert_eq!(metered.total_provided_btu(), 30);
+		assert_eq!(mock.remaining(), 0);
+		mock.assert_received(&[1, 2]);
+	}
+
+	#[test]
+	#[should_panic(expected = "ran out of scripted outputs")]
+	fn mock_provider_panics_when_exhausted() {
+		let mock = MockProvider::<Diesel>::new([]);
+		mock.provide_energy(FuelContainer::new(1));
+	}
+
+	#[test]
+	fn generator_builder_composes_stages() {
+		let plain = GeneratorBuilder::new().build::<Uranium>();
+		assert_eq!(plain.provide_energy(FuelContainer::new(10)).to_btu(), 10000);
+		assert_eq!(plain.total_provided_btu(), None);
+
+		let generator = GeneratorBuilder::new()
+			.efficiency(90)
+			.decay::<LinearByCalls>()
+			.warm_up::<1>(50)
+			.throttle(800)
+			.metered()
+			.build::<Diesel>();
+		let outputs = (0..3)
+			.map(|_| generator.provide_energy(FuelContainer::new(10)).to_btu())
+			.collect::<Vec<_>>();
+
+		assert_eq!(outputs, vec![450, 800, 800]);
+		assert_eq!(generator.current_efficiency(), 87);
+		assert_eq!(generator.total_provided_btu(), Some(2050));
+		assert_eq!(generator.calls(), Some(3));
+	}
+
+	#[test]
+	fn generator_builder_wears_by_fuel_units() {
+		let generator = GeneratorBuilder::new()
+			.efficiency(85)
+			.decay::<LinearByFuel<100>>()
+			.metered()
+			.build::<Diesel>();
+		let engine = WearByFuel::<100>::new(85);
+
+		for _ in 0..25 {
+			assert_eq!(
+				generator.provide_energy(FuelContainer::new(10)).to_btu(),
+				engine
+					.provide_energy(FuelContainer::<Diesel>::new(10))
+					.to_btu()
+			);
+		}
+		assert_eq!(generator.current_efficiency(), 83);
+		assert_eq!(
+			generator.current_efficiency(),
+			ProvideEnergy::<Diesel>::current_efficiency(&engine)
+		);
+	}
+
+	#[test]
+	fn generator_pool_rotates() {
+		let pool = GeneratorPool::<Diesel>::new(Rotation::RoundRobin)
+			.with(OmniGenerator::<100>)
+			.with(OmniGenerator::<50>);
+		let outputs = [10, 10, 20]
+			.into_iter()
+			.map(|amount| pool.provide_energy(FuelContainer::new(amount)).to_btu())
+			.collect::<Vec<_>>();
+		assert_eq!(outputs, vec![1000, 500, 2000]);
+		assert_eq!(pool.fuel_burned(), vec![30, 10]);
+
+		let pool = GeneratorPool::<Diesel>::new(Rotation::LeastWorn)
+			.with(OmniGenerator::<100>)
+			.with(OmniGenerator::<50>);
+		let outputs = [30, 10, 10, 20]
+			.into_iter()
+			.map(|amount| pool.provide_energy(FuelContainer::new(amount)).to_btu())
+			.collect::<Vec<_>>();
+		assert_eq!(outputs, vec![3000, 500, 500, 1000]);
+		assert_eq!(pool.fuel_burned(), vec![30, 40]);
+
+		let empty = GeneratorPool::<Diesel>::new(Rotation::RoundRobin);
+		assert_eq!(empty.provide_energy(FuelContainer::new(10)).to_btu(), 0);
+	}
+
+	#[test]
+	fn engine_lifecycle() {
+		let engine = Engine::new(90).start();
 		assert_eq!(
-			nr.provide_energy(FuelContainer::<Uranium>::new(10))
+			ProvideEnergy::<Diesel>::provide_energy_n(&engine, 10, 5),
+			900 + 890 + 880 + 870 + 860
+		);
+
+		let engine = engine.stop();
+		assert_eq!(engine.efficiency(), 85);
+		let engine = engine.service().finish();
+		assert_eq!(engine.efficiency(), 90);
+
+		let engine = engine.start();
+		assert_eq!(
+			engine
+				.provide_energy(FuelContainer::<Diesel>::new(10))
 				.to_btu(),
-			9900
+			900
 		);
+	}
+
+	#[test]
+	fn checkpoint_and_restore() {
+		let plant = Metered::new(WarmUp::<_, 2>::new(InternalCombustion::<1>::new(90), 50));
+		ProvideEnergy::<Diesel>::provide_energy_n(&plant, 10, 3);
+
+		let state = plant.checkpoint();
+		assert_eq!(state.calls, 3);
+		assert_eq!(state.provider.provisions, 2);
+		assert_eq!(state.provider.provider.efficiency, 87);
+
+		let restored = Metered::<WarmUp<InternalCombustion<1>, 2>>::restore(state.clone());
+		assert_eq!(restored.checkpoint(), state);
 		assert_eq!(
-			nr.provide_energy(FuelContainer::<Uranium>::new(10))
+			restored
+				.provide_energy(FuelContainer::<Diesel>::new(10))
 				.to_btu(),
-			9900
+			plant
+				.provide_energy(FuelContainer::<Diesel>::new(10))
+				.to_btu()
 		);
+		assert_eq!(restored.checkpoint(), plant.checkpoint());
+	}
+
+	#[cfg(feature = "async")]
+	fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+		struct ThreadWaker(std::thread::Thread);
+		impl std::task::Wake for ThreadWaker {
+			fn wake(self: std::sync::Arc<Self>) {
+				self.0.unpark();
+			}
+		}
+
+		let waker = std::sync::Arc::new(ThreadWaker(std::thread::current())).into();
+		let mut cx = std::task::Context::from_waker(&waker);
+		let mut future = Box::pin(future);
+		loop {
+			match future.as_mut().poll(&mut cx) {
+				std::task::Poll::Ready(output) => return output,
+				std::task::Poll::Pending => std::thread::park(),
+			}
+		}
 	}
 
+	#[cfg(feature = "async")]
 	#[test]
-	fn ic_1() {
-		let ic = InternalCombustion::<3>::new(120);
+	fn async_adapter_with_latency() {
+		let latency = std::time::Duration::from_millis(20);
+		let reactor = AsyncAdapter::with_latency(NuclearReactor, latency);
+
+		let start = std::time::Instant::now();
+		let energy = block_on(reactor.provide_energy_async(FuelContainer::<Uranium>::new(10)));
+
+		assert!(start.elapsed() >= latency);
+		assert_eq!(energy.to_btu(), 9900);
+	}
+
+	#[cfg(feature = "async")]
+	#[test]
+	fn delay_wakes_the_latest_waker() {
+		use std::future::Future;
+		use std::sync::atomic::{AtomicBool, Ordering};
+		use std::sync::Arc;
+
+		struct Flag(AtomicBool);
+		impl std::task::Wake for Flag {
+			fn wake(self: Arc<Self>) {
+				self.0.store(true, Ordering::SeqCst);
+			}
+		}
+
+		let first = Arc::new(Flag(AtomicBool::new(false)));
+		let second = Arc::new(Flag(AtomicBool::new(false)));
+		let (first_waker, second_waker) = (first.clone().into(), second.clone().into());
+		let mut delay = Delay::new(std::time::Duration::from_millis(20));
+		let mut delay = std::pin::Pin::new(&mut delay);
+
+		let pending = delay
+			.as_mut()
+			.poll(&mut std::task::Context::from_waker(&first_waker));
+		assert!(pending.is_pending());
+		let pending = delay
+			.as_mut()
+			.poll(&mut std::task::Context::from_waker(&second_waker));
+		assert!(pending.is_pending());
+
+		std::thread::sleep(std::time::Duration::from_millis(200));
+		assert!(second.0.load(Ordering::SeqCst));
+		assert!(!first.0.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn precise_generator() {
+		let pg = PreciseGenerator::<995, 1000>;
 		assert_eq!(
-			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
-			1000
+			pg.provide_energy(FuelContainer::<Uranium>::new(10))
+				.to_btu(),
+			9950
 		);
+
+		// With a denominator of 100 it is the same as the `OmniGenerator`.
 		assert_eq!(
-			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
-			1000
+			PreciseGenerator::<80, 100>
+				.provide_energy(FuelContainer::<LithiumBattery>::new(7))
+				.to_btu(),
+			OmniGenerator::<80>
+				.provide_energy(FuelContainer::<LithiumBattery>::new(7))
+				.to_btu()
 		);
+	}
+
+	#[test]
+	fn runtime_generator() {
+		let rg = RuntimeGenerator::new(80);
 		assert_eq!(
-			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
-			1000
+			rg.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
+			800
 		);
+
+		let saturated = RuntimeGenerator::new(200);
+		assert_eq!(saturated.efficiency(), 100);
 		assert_eq!(
-			ic.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu(),
-			990
+			saturated
+				.provide_energy(FuelContainer::<Uranium>::new(10))
+				.to_btu(),
+			10000
+		);
+	}
+
+	#[test]
+	fn output_saturates() {
+		let rg = RuntimeGenerator::new(100);
+		let hydrogen = |amount| rg.provide_energy(FuelContainer::<Hydrogen>::new(amount));
+		assert_eq!(hydrogen(14_316_557), 4_294_967_100);
+		assert_eq!(hydrogen(14_316_558), BTU::MAX);
+		assert_eq!(hydrogen(20_000_000), BTU::MAX);
+		assert_eq!(
+			PreciseGenerator::<995, 1000>.provide_energy(FuelContainer::<Hydrogen>::new(u32::MAX)),
+			BTU::MAX
+		);
+	}
+
+	#[test]
+	fn provide_energy_from_leaves_leftovers() {
+		let og = OmniGenerator::<100>;
+		let mut container = FuelContainer::<Diesel>::new(10);
+
+		assert_eq!(og.provide_energy_from(&mut container, 4).to_btu(), 400);
+		assert_eq!(container.amount(), 6);
+		assert_eq!(og.provide_energy_from(&mut container, 100).to_btu(), 600);
+		assert_eq!(container.amount(), 0);
+	}
+
+	#[test]
+	fn providers_behind_pointers() {
+		fn provide<P: ProvideEnergy<Diesel>>(p: P) -> BTU {
+			p.provide_energy(FuelContainer::<Diesel>::new(10)).to_btu()
+		}
+
+		let og = OmniGenerator::<100>;
+		assert_eq!(provide(&og), 1000);
+		assert_eq!(provide(Box::new(OmniGenerator::<90>)), 900);
+		assert_eq!(provide(std::rc::Rc::new(OmniGenerator::<80>)), 800);
+		assert_eq!(provide(std::sync::Arc::new(OmniGenerator::<70>)), 700);
+
+		let boxed: Box<dyn ProvideEnergy<Diesel>> = Box::new(NuclearReactor);
+		assert_eq!(provide(boxed), 990);
+
+		// Stateful providers keep their state when shared.
+		let ic = std::rc::Rc::new(InternalCombustion::<3>::new(100));
+		assert_eq!(provide(ic.clone()), 1000);
+		assert_eq!(provide(ic), 990);
+	}
+
+	#[test]
+	fn detailed_provision_reports_waste_heat() {
+		let detailed =
+			OmniGenerator::<80>.provide_energy_detailed(FuelContainer::<Diesel>::new(10));
+		assert_eq!(detailed.useful.to_btu(), 800);
+		assert_eq!(detailed.waste_heat, 200);
+
+		let detailed = NuclearReactor.provide_energy_detailed(FuelContainer::<Uranium>::new(10));
+		assert_eq!(detailed.useful.to_btu(), 9900);
+		assert_eq!(detailed.waste_heat, 100);
+	}
+
+	#[test]
+	fn effective_density_ranks_providers() {
+		fn density<P: ProvideEnergy<Uranium>>(p: &P) -> BTU {
+			p.effective_density()
+		}
+
+		assert_eq!(density(&NuclearReactor), 990);
+		assert_eq!(density(&OmniGenerator::<80>), 800);
+		assert_eq!(density(&RuntimeGenerator::new(50)), 500);
+		assert_eq!(density(&PreciseGenerator::<1, 3>), 330);
+		assert_eq!(density(&Metered::new(NuclearReactor)), 990);
+		assert_eq!(density(&NuclearReactor.chain(OmniGenerator::<50>)), 490);
+		assert_eq!(density(&WarmUp::<_, 1>::new(OmniGenerator::<100>, 20)), 800);
+		assert_eq!(density(&CurvedEngine::<_, Flat<70>>(PhantomData)), 700);
+		let combined = CombinedCycle::new(OmniGenerator::<60>, OmniGenerator::<50>);
+		assert_eq!(density(&combined), 800);
+		assert_eq!(density(&MockProvider::new([]).with_efficiency(30)), 300);
+		let half =
+			FnProvider::new(|f: FuelContainer<Uranium>| OmniGenerator::<50>.provide_energy(f));
+		assert_eq!(density(&half), 500);
+
+		// Querying the density does not wear the engine down.
+		let ic = InternalCombustion::<1>::new(90);
+		assert_eq!(density(&ic), 900);
+		assert_eq!(density(&ic), 900);
+		ic.provide_energy(FuelContainer::<Uranium>::new(1));
+		assert_eq!(density(&ic), 890);
+	}
+
+	#[test]
+	fn provide_energy_n_sums_with_decay() {
+		let ic = InternalCombustion::<1>::new(90);
+		assert_eq!(
+			ProvideEnergy::<Diesel>::provide_energy_n(&ic, 10, 3),
+			900 + 890 + 880
+		);
+		assert_eq!(ic.efficiency(), 87);
+		assert_eq!(
+			ProvideEnergy::<Diesel>::provide_energy_n(&OmniGenerator::<100>, 10, 0),
+			0
+		);
+	}
+
+	#[test]
+	fn par_batch_matches_sequential() {
+		let og = OmniGenerator::<80>;
+		let batch = (0..1000)
+			.map(|_| FuelContainer::<Diesel>::new(3))
+			.collect::<Vec<_>>();
+		let sequential: BTU = (0..1000)
+			.map(|_| og.provide_energy(FuelContainer::<Diesel>::new(3)).to_btu())
+			.sum();
+
+		assert_eq!(og.provide_energy_par_batch(batch).to_btu(), sequential);
+		assert_eq!(
+			og.provide_energy_par_batch(Vec::<FuelContainer<Diesel>>::new())
+				.to_btu(),
+			0
 		);
 	}
 
+	#[test]
+	fn ic_clone_vs_fresh() {
+		let ic = InternalCombustion::<1>::new(90);
+		ProvideEnergy::<Diesel>::provide_energy_n(&ic, 10, 5);
+
+		let snapshot = ic.clone();
+		let fresh = ic.fresh();
+		assert_eq!(snapshot.efficiency(), 85);
+		assert_eq!(fresh.efficiency(), 90);
+
+		// The snapshot evolves independently from the original.
+		snapshot.provide_energy(FuelContainer::<Diesel>::new(10));
+		assert_eq!(snapshot.efficiency(), 84);
+		assert_eq!(ic.efficiency(), 85);
+	}
+
+	#[test]
+	fn decay_policies() {
+		let provide =
+			|ic: &dyn ProvideEnergy<Diesel>| ic.provide_energy(FuelContainer::new(10)).to_btu();
+
+		let by_calls = InternalCombustion::<1, LinearByCalls>::new(90);
+		let no_decay = InternalCombustion::<1, NoDecay>::new(90);
+		for _ in 0..5 {
+			provide(&by_calls);
+			assert_eq!(provide(&no_decay), 900);
+		}
+		assert_eq!(by_calls.efficiency(), 85);
+		assert_eq!(no_decay.efficiency(), 90);
+	}
+
+	#[test]
+	fn wear_by_fuel() {
+		let engine = WearByFuel::<100>::new(90);
+		let provide = |amount| {
+			engine
+				.provide_energy(FuelContainer::<Diesel>::new(amount))
+				.to_btu()
+		};
+
+		// Many small provisions wear the engine as much as a single big one.
+		for _ in 0..10 {
+			assert_eq!(provide(10), 900);
+		}
+		assert_eq!(engine.efficiency(), 89);
+		assert_eq!(provide(250), 22250);
+		assert_eq!(engine.efficiency(), 87);
+
+		provide(10_000);
+		assert_eq!(engine.efficiency(), 0);
+		assert_eq!(provide(10), 0);
+	}
+
 	#[test]
 	fn omni_1() {
 		let og = OmniGenerator::<100>;
@@ -373,6 +3942,270 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn mixed_as_keeps_units() {
+		let density: Joule = MixedAs::<Diesel, LithiumBattery>::energy_density();
+		assert_eq!(density, Joule(150 * 1055));
+
+		let density: Calorie = MixedAs::<Diesel, LithiumBattery, Calorie>::energy_density();
+		assert_eq!(density, Calorie(150 * 251));
+
+		assert_eq!(
+			MixedAs::<Diesel, LithiumBattery, BTU>::energy_density(),
+			Mixed::<Diesel, LithiumBattery>::energy_density()
+		);
+		assert_eq!(
+			OmniGenerator::<100>.provide_energy(FuelContainer::<MixedAs<Diesel, Uranium>>::new(2)),
+			Joule(1100 * 1055)
+		);
+	}
+
+	#[test]
+	fn n_way_mixes() {
+		assert_eq!(
+			Mixed3::<Diesel, LithiumBattery, Uranium>::energy_density(),
+			(100 + 200 + 1000) / 3
+		);
+		assert_eq!(
+			MixedN::<(Diesel, LithiumBattery)>::energy_density(),
+			Mixed::<Diesel, LithiumBattery>::energy_density()
+		);
+		assert_eq!(
+			MixedN::<(Diesel, LithiumBattery, Uranium, Diesel)>::energy_density(),
+			Mixed::<Mixed<Diesel, LithiumBattery>, Mixed<Uranium, Diesel>>::energy_density()
+		);
+	}
+
+	#[test]
+	fn custom_mixed_bounds() {
+		assert_eq!(
+			CustomMixed::<100, Diesel, LithiumBattery>::energy_density(),
+			100
+		);
+		assert_eq!(
+			CustomMixed::<0, Diesel, LithiumBattery>::energy_density(),
+			200
+		);
+	}
+
+	#[test]
+	fn custom_mixed_rounding() {
+		// half of 1 and half of 100 is 50.5.
+		assert_eq!(CustomMixed::<50, Electricity, Diesel>::energy_density(), 51);
+		assert_eq!(
+			CustomMixed::<50, Electricity, Diesel, FloorEach>::energy_density(),
+			50
+		);
+
+		// 1% of 1000 and 99% of 101 is 109.99, truncating each term loses almost a whole BTU.
+		assert_eq!(RoundNearest::mix(1000, 101, 1), 110);
+		assert_eq!(FloorEach::mix(1000, 101, 1), 109);
+	}
+
+	#[test]
+	fn ratio_mixed() {
+		// a third of 100 and two thirds of 200 is 166.67.
+		assert_eq!(
+			RatioMixed::<1, 3, Diesel, LithiumBattery>::energy_density(),
+			167
+		);
+		assert_eq!(
+			RatioMixed::<2, 3, Diesel, LithiumBattery>::energy_density(),
+			133
+		);
+		assert_eq!(
+			RatioMixed::<0, 7, Diesel, LithiumBattery>::energy_density(),
+			200
+		);
+		assert_eq!(
+			RatioMixed::<7, 7, Diesel, LithiumBattery>::energy_density(),
+			100
+		);
+		assert_eq!(
+			RatioMixed::<30, 100, Diesel, Uranium>::energy_density(),
+			CustomMixed::<30, Diesel, Uranium>::energy_density()
+		);
+	}
+
+	#[test]
+	fn mix_fuels_macro() {
+		type Blend = mix_fuels!(Diesel => 50, Ethanol => 30, Hydrogen => 20);
+		assert_eq!(
+			Blend::energy_density(),
+			(100 * 50 + 80 * 30 + 300 * 20) / 100
+		);
+
+		type Even = mix_fuels!(Diesel => 50, LithiumBattery => 50);
+		assert_eq!(
+			Even::energy_density(),
+			Mixed::<Diesel, LithiumBattery>::energy_density()
+		);
+
+		// inner coefficients that are not whole percents, 30 out of 60 and 20 out of 30.
+		type Fine = mix_fuels!(Diesel => 40, Ethanol => 30, Hydrogen => 20, Uranium => 10,);
+		assert_eq!(
+			Fine::energy_density(),
+			(100 * 40 + 80 * 30 + 300 * 20 + 1000 * 10) / 100
+		);
+	}
+
+	#[test]
+	fn blend_builder() {
+		type Built = Blend<Cons<Hydrogen, 20, Cons<Ethanol, 30, Cons<Diesel, 50, Nil>>>>;
+		assert_eq!(
+			Built::energy_density(),
+			<mix_fuels!(Diesel => 50, Ethanol => 30, Hydrogen => 20)>::energy_density()
+		);
+		assert_eq!(
+			Blend::<Cons<Diesel, 1, Cons<LithiumBattery, 1, Nil>>>::energy_density(),
+			EvenBlend::<Diesel, LithiumBattery>::energy_density()
+		);
+
+		// a blend is a fuel, so it nests in other mixes.
+		let nested = Blend::new()
+			.with::<Built, 1>()
+			.with::<EvenBlend<Uranium, Diesel>, 1>();
+		let energy = OmniGenerator::<50>.provide_energy(nested.container(4));
+		assert_eq!(energy, (134 + 550) / 2 * 4 / 2);
+	}
+
+	#[test]
+	fn mixture_info() {
+		assert_eq!(
+			Mixed::<Diesel, Ethanol>::components(),
+			vec![("Diesel", 50), ("Ethanol", 50)]
+		);
+		assert_eq!(
+			CustomMixed::<85, Ethanol, Diesel>::components(),
+			vec![("Ethanol", 85), ("Diesel", 15)]
+		);
+		assert_eq!(
+			RatioMixed::<2, 3, Hydrogen, Uranium>::components(),
+			vec![("Hydrogen", 67), ("Uranium", 33)]
+		);
+	}
+
+	#[test]
+	fn mixes_keep_shared_markers() {
+		fn renewable<F: IsRenewable>(_: PhantomData<F>) {}
+		fn combustible<F: Combustible>(_: PhantomData<F>) {}
+
+		renewable(PhantomData::<CustomMixed<30, Ethanol, LithiumBattery>>);
+		renewable(PhantomData::<EvenBlend<EvenBlend<Ethanol, LithiumBattery>, Ethanol>>);
+		combustible(PhantomData::<CustomMixed<80, Diesel, Ethanol>>);
+		combustible(PhantomData::<CustomMixed<10, Hydrogen, Ethanol, FloorEach>>);
+	}
+
+	#[test]
+	fn green_engine_burns_renewables() {
+		let engine = GreenEngine::<LithiumBattery>(PhantomData);
+		assert_eq!(engine.provide_energy(FuelContainer::new(10)).to_btu(), 2000);
+
+		let engine = GreenEngine::<EvenBlend<Ethanol, LithiumBattery>>(PhantomData);
+		assert_eq!(engine.provide_energy(FuelContainer::new(10)), 1400);
+	}
+
+	#[test]
+	fn renewability_scores() {
+		fn clears_threshold<F: IsRenewable + RenewabilityScore>() -> bool {
+			F::score() >= RENEWABLE_THRESHOLD
+		}
+		assert!(clears_threshold::<LithiumBattery>());
+		assert!(clears_threshold::<Ethanol>());
+		assert!(clears_threshold::<CustomMixed<40, Ethanol, LithiumBattery>>());
+
+		assert_eq!(Mixed::<Diesel, Ethanol>::score(), 30);
+		assert_eq!(CustomMixed::<25, Diesel, LithiumBattery>::score(), 53);
+
+		let engine = ThresholdGreenEngine::<Hydrogen, 50>(PhantomData);
+		assert_eq!(engine.provide_energy(FuelContainer::new(2)), 600);
+		let engine = ThresholdGreenEngine::<Ethanol>(PhantomData);
+		assert_eq!(engine.provide_energy(FuelContainer::new(2)).to_btu(), 160);
+	}
+
+	#[test]
+	fn offsets_ledger() {
+		fn neutral<F: CarbonNeutral>() -> u32 {
+			F::grams_per_kbtu()
+		}
+		assert_eq!(neutral::<Ethanol>(), 0);
+		assert_eq!(neutral::<LithiumBattery>(), 0);
+		assert_eq!(neutral::<CustomMixed<70, Ethanol, LithiumBattery>>(), 0);
+
+		let engine = Accounted::new(OmniGenerator::<100>);
+		engine.provide_energy(FuelContainer::<Diesel>::new(100));
+		engine.provide_energy(FuelContainer::<Ethanol>::new(100));
+		engine.provide_energy(FuelContainer::<EvenBlend<Diesel, Ethanol>>::new(100));
+		assert_eq!(
+			engine.offsets().debits(),
+			&[
+				("Diesel", 740),
+				(std::any::type_name::<EvenBlend<Diesel, Ethanol>>(), 333)
+			]
+		);
+		assert_eq!(engine.offsets().gross_emissions(), 1073);
+
+		engine.offset(1000);
+		assert_eq!(engine.offsets().net_emissions(), 73);
+		engine.offset(100);
+		assert_eq!(engine.offsets().net_emissions(), -27);
+	}
+
+	#[test]
+	fn green_british_engine() {
+		let engine = GreenBritishEngine::<CustomMixed<40, Ethanol, LithiumBattery>>(PhantomData);
+		assert_eq!(engine.provide_energy(FuelContainer::new(10)), 1520);
+	}
+
+	#[test]
+	fn legacy_engine_burns_fossils() {
+		assert_eq!(
+			LegacyEngine::<Coal>(PhantomData)
+				.provide_energy(FuelContainer::new(10))
+				.to_btu(),
+			1000
+		);
+		assert_eq!(
+			LegacyEngine::<NaturalGas>(PhantomData).provide_energy(FuelContainer::new(10)),
+			480
+		);
+		assert_eq!(
+			LegacyEngine::<Gasoline>(PhantomData)
+				.provide_energy(FuelContainer::new(10))
+				.to_btu(),
+			440
+		);
+		assert_eq!(LegacyEngine::<Diesel>(PhantomData).current_efficiency(), 40);
+	}
+
+	#[test]
+	fn runtime_mixer() {
+		let error = RuntimeMixer::new(100, 200, 101).unwrap_err();
+		assert_eq!(error, MixError::CoefficientTooLarge(101));
+		assert_eq!(
+			error.to_string(),
+			"coefficient must be at most 100, not 101"
+		);
+
+		let mixer = RuntimeMixer::of::<Diesel, LithiumBattery>(30).unwrap();
+		assert_eq!(mixer.coefficient(), 30);
+		assert_eq!(
+			mixer.density_btu(),
+			CustomMixed::<30, Diesel, LithiumBattery>::energy_density()
+		);
+		assert_eq!(RuntimeMixer::new(100, 200, 100).unwrap().density_btu(), 100);
+		assert_eq!(RuntimeMixer::new(100, 200, 0).unwrap().density_btu(), 200);
+
+		let generator = OmniGenerator::<50>;
+		assert_eq!(mixer.provide_with(&generator, 10), 170 * 10 / 2);
+
+		let dense = RuntimeMixer::new(100_000, 100_000, 50).unwrap();
+		assert_eq!(dense.provide_with(&generator, 100_000), BTU::MAX);
+		let engine = WearByFuel::<10>::new(90);
+		assert_eq!(mixer.provide_with(&engine, 10), 170 * 10 * 90 / 100);
+		assert_eq!(engine.efficiency(), 89);
+	}
+
 	#[test]
 	fn custom_mixed_1() {
 		// custom with 50 is the same as Mixed.
@@ -1,52 +1,421 @@
 // Imagine you have an outcome enum like this.
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Outcome {
 	Ok,
 	SomethingWentWrong,
 	IDontKnow,
 }
 
+impl std::fmt::Display for Outcome {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Outcome::Ok => write!(f, "ok"),
+			Outcome::SomethingWentWrong => write!(f, "something went wrong"),
+			Outcome::IDontKnow => write!(f, "i don't know"),
+		}
+	}
+}
+
+impl Outcome {
+	/// `Ok(())` if this outcome is ok, such that processing loops can bail out early with `?`.
+	pub fn ok_or(self) -> Result<(), OutcomeError> {
+		match self {
+			Outcome::Ok => Ok(()),
+			Outcome::SomethingWentWrong => Err(OutcomeError::SomethingWentWrong),
+			Outcome::IDontKnow => Err(OutcomeError::IDontKnow),
+		}
+	}
+}
+
+/// Why an [`Outcome`] was not ok.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OutcomeError {
+	SomethingWentWrong,
+	IDontKnow,
+}
+
+impl std::fmt::Display for OutcomeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			OutcomeError::SomethingWentWrong => write!(f, "something went wrong"),
+			OutcomeError::IDontKnow => write!(f, "the outcome is unknown"),
+		}
+	}
+}
+
+impl std::error::Error for OutcomeError {}
+
+impl From<Outcome> for Result<(), OutcomeError> {
+	fn from(outcome: Outcome) -> Self {
+		outcome.ok_or()
+	}
+}
+
+/// Failures of processing, either of a single item or of a whole batch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProcessingError {
+	Item(OutcomeError),
+	Batch(BatchError),
+}
+
+impl std::fmt::Display for ProcessingError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ProcessingError::Item(_) => write!(f, "processing an item failed"),
+			ProcessingError::Batch(_) => write!(f, "processing a batch failed"),
+		}
+	}
+}
+
+impl std::error::Error for ProcessingError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ProcessingError::Item(error) => Some(error),
+			ProcessingError::Batch(error) => Some(error),
+		}
+	}
+}
+
+impl From<OutcomeError> for ProcessingError {
+	fn from(error: OutcomeError) -> Self {
+		ProcessingError::Item(error)
+	}
+}
+
+impl From<BatchError> for ProcessingError {
+	fn from(error: BatchError) -> Self {
+		ProcessingError::Batch(error)
+	}
+}
+
+impl From<Outcome> for Result<(), ProcessingError> {
+	fn from(outcome: Outcome) -> Self {
+		Ok(outcome.ok_or()?)
+	}
+}
+
+/// Some items of a batch went wrong.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchError {
+	/// The indices of the failed items.
+	pub failed: Vec<usize>,
+	/// The size of the batch.
+	pub total: usize,
+}
+
+impl std::fmt::Display for BatchError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} of {} items went wrong, at",
+			self.failed.len(),
+			self.total
+		)?;
+		for index in &self.failed {
+			write!(f, " #{index}")?;
+		}
+		Ok(())
+	}
+}
+
+impl std::error::Error for BatchError {}
+
+/// `Ok(())` unless something went wrong with some of the `outcomes`. Unknown outcomes are not
+/// failures.
+pub fn ensure_all_ok(outcomes: &[Outcome]) -> Result<(), BatchError> {
+	let (_, failed, _) = outcomes.partition_outcomes();
+	if failed.is_empty() {
+		Ok(())
+	} else {
+		Err(BatchError {
+			failed,
+			total: outcomes.len(),
+		})
+	}
+}
+
+/// Like [`Outcome`], but able to say what the result or the problem was.
+///
+/// With the default, empty payloads, it carries as much information as an [`Outcome`], and the two
+/// convert into each other.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DetailedOutcome<T = (), E = ()> {
+	Ok(T),
+	Failed(E),
+	Unknown,
+}
+
+impl From<Outcome> for DetailedOutcome {
+	fn from(outcome: Outcome) -> Self {
+		match outcome {
+			Outcome::Ok => Self::Ok(()),
+			Outcome::SomethingWentWrong => Self::Failed(()),
+			Outcome::IDontKnow => Self::Unknown,
+		}
+	}
+}
+
+/// Drops the payloads.
+impl<T, E> From<DetailedOutcome<T, E>> for Outcome {
+	fn from(outcome: DetailedOutcome<T, E>) -> Self {
+		outcome.as_outcome()
+	}
+}
+
+/// `Ok` is, well, ok, and any error means something went wrong.
+impl<T, E> From<Result<T, E>> for Outcome {
+	fn from(result: Result<T, E>) -> Self {
+		match result {
+			Ok(_) => Outcome::Ok,
+			Err(_) => Outcome::SomethingWentWrong,
+		}
+	}
+}
+
+impl<T, E> From<Result<T, E>> for DetailedOutcome<T, E> {
+	fn from(result: Result<T, E>) -> Self {
+		match result {
+			Ok(value) => Self::Ok(value),
+			Err(error) => Self::Failed(error),
+		}
+	}
+}
+
+/// The iterator returned by [`MapResultsToOutcomes::map_results_to_outcomes`].
+pub type ResultsToOutcomes<I, T, E> = core::iter::Map<I, fn(Result<T, E>) -> Outcome>;
+
+/// Turns a pipeline of `Result`s into one of outcomes, ready to be counted.
+pub trait MapResultsToOutcomes<T, E>: Iterator<Item = Result<T, E>> + Sized {
+	fn map_results_to_outcomes(self) -> ResultsToOutcomes<Self, T, E> {
+		self.map(Outcome::from)
+	}
+}
+
+impl<T, E, I: Iterator<Item = Result<T, E>>> MapResultsToOutcomes<T, E> for I {}
+
+/// Anything that can be counted as an [`Outcome`].
+pub trait AsOutcome {
+	fn as_outcome(&self) -> Outcome;
+}
+
+impl AsOutcome for Outcome {
+	fn as_outcome(&self) -> Outcome {
+		*self
+	}
+}
+
+impl<O: AsOutcome + ?Sized> AsOutcome for &O {
+	fn as_outcome(&self) -> Outcome {
+		(**self).as_outcome()
+	}
+}
+
+impl<T, E> AsOutcome for DetailedOutcome<T, E> {
+	fn as_outcome(&self) -> Outcome {
+		match self {
+			Self::Ok(_) => Outcome::Ok,
+			Self::Failed(_) => Outcome::SomethingWentWrong,
+			Self::Unknown => Outcome::IDontKnow,
+		}
+	}
+}
+
 // A function takes some arbitrary input that's a collection of `T`, and processes each item
 // individually. Each process can be an `Outcome`. We return `Vec<Outcome>`.
 
 // pub fn process_stuff<T>(input: impl Iterator<Item = T>) -> Vec<Outcome> {
 // 	unimplemented!("You are not expected to implement this function");
 // }
-pub fn process_stuff<T: std::fmt::Debug>(input: impl Iterator<Item = T>) -> Vec<Outcome> 
-where 
-T: std::fmt::Debug + std::cmp::PartialEq + std::ops::Rem<Output = T> + From<u8> + Copy {
-    let mut outcomes = Vec::new();
-    
-    for item in input {
-        let i: T = item.into();
+pub fn process_stuff<T: Classify>(input: impl Iterator<Item = T>) -> Vec<Outcome> {
+	process_stuff_with(input, T::classify)
+}
+
+/// How [`process_stuff`] decides the outcome of an item. Any type can opt in.
+pub trait Classify {
+	fn classify(&self) -> Outcome;
+}
+
+// Even numbers are ok, and three is a problem. Nobody knows about the rest.
+macro_rules! impl_classify_for_integers {
+	($($int:ty),+) => {
+		$(
+			impl Classify for $int {
+				fn classify(&self) -> Outcome {
+					match *self {
+						num if num % 2 == 0 => Outcome::Ok,
+						3 => Outcome::SomethingWentWrong,
+						_ => Outcome::IDontKnow,
+					}
+				}
+			}
+		)+
+	};
+}
+
+impl_classify_for_integers!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
 
-        match i {
-            num if num % 2.into() == 0.into() => outcomes.push(Outcome::Ok),
-            num if num == 3.into() => outcomes.push(Outcome::SomethingWentWrong),
-            _ => outcomes.push(Outcome::IDontKnow),
-        }
-    }
+impl<T: Classify + ?Sized> Classify for &T {
+	fn classify(&self) -> Outcome {
+		(**self).classify()
+	}
+}
+
+/// Like [`process_stuff`], but for any item type, classified by `classify`.
+pub fn process_stuff_with<T>(
+	input: impl Iterator<Item = T>,
+	classify: impl FnMut(&T) -> Outcome,
+) -> Vec<Outcome> {
+	input.process_with(classify).collect()
+}
 
-	outcomes
+/// Wraps a classifier, and reports every item it classifies as [`Outcome::SomethingWentWrong`] to
+/// a log sink, along with the index of the item.
+///
+/// ```
+/// use pba_qualifier_exam::i_extension_traits::*;
+///
+/// let mut warnings = Vec::new();
+/// let mut observed = ObservedClassifier::new("jobs", u32::classify, |target: &str, message: &str| {
+///     warnings.push(format!("{target}: {message}"))
+/// });
+/// process_stuff_with(1..5u32, |item| observed.classify(item));
+///
+/// assert_eq!(warnings, vec!["jobs: item #2: something went wrong"]);
+/// ```
+pub struct ObservedClassifier<C, S> {
+	target: &'static str,
+	classify: C,
+	sink: S,
+	index: usize,
 }
 
+impl<C, S: FnMut(&str, &str)> ObservedClassifier<C, S> {
+	/// Classify with `classify`, and send warnings about failures to `sink`, as a target and a
+	/// message.
+	pub fn new(target: &'static str, classify: C, sink: S) -> Self {
+		Self {
+			target,
+			classify,
+			sink,
+			index: 0,
+		}
+	}
+
+	pub fn classify<T>(&mut self, item: &T) -> Outcome
+	where
+		C: FnMut(&T) -> Outcome,
+	{
+		let outcome = (self.classify)(item);
+		if outcome == Outcome::SomethingWentWrong {
+			(self.sink)(self.target, &format!("item #{}: {}", self.index, outcome));
+		}
+		self.index += 1;
+		outcome
+	}
+}
+
+/// Where [`OutcomeCount::log_failures`] sends its warnings, as a target and a message.
+#[cfg(feature = "tracing")]
+pub type LogSink = Box<dyn Fn(&str, &str) + Send + Sync>;
+
+#[cfg(feature = "tracing")]
+static LOG_SINK: std::sync::RwLock<Option<LogSink>> = std::sync::RwLock::new(None);
+
+/// Send the warnings of [`OutcomeCount::log_failures`] to `sink`, e.g. to forward them to a
+/// logging framework. Until then, they are written to standard error.
+#[cfg(feature = "tracing")]
+pub fn set_log_sink(sink: impl Fn(&str, &str) + Send + Sync + 'static) {
+	*LOG_SINK.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(sink));
+}
+
+#[cfg(feature = "tracing")]
+fn warn(target: &str, message: &str) {
+	match &*LOG_SINK.read().unwrap_or_else(|e| e.into_inner()) {
+		Some(sink) => sink(target, message),
+		None => eprintln!("WARN {target}: {message}"),
+	}
+}
+
+/// A second pass over processed `items`: re-run `retry` on the items whose outcome is unknown,
+/// and update their outcome in place. The other outcomes are left alone.
+///
+/// Panics if there is not exactly one outcome per item.
+pub fn retry_unknowns<T>(
+	items: &[T],
+	outcomes: &mut [Outcome],
+	mut retry: impl FnMut(&T) -> Outcome,
+) {
+	assert_eq!(items.len(), outcomes.len(), "expected one outcome per item");
+	for (item, outcome) in items.iter().zip(outcomes.iter_mut()) {
+		if *outcome == Outcome::IDontKnow {
+			*outcome = retry(item);
+		}
+	}
+}
+
+/// Classifies the items of an iterator lazily, see [`ProcessWith::process_with`].
+pub struct ProcessStuff<I, C> {
+	input: I,
+	classify: C,
+}
+
+impl<I: Iterator, C: FnMut(&I::Item) -> Outcome> Iterator for ProcessStuff<I, C> {
+	type Item = Outcome;
+
+	fn next(&mut self) -> Option<Outcome> {
+		self.input.next().map(|item| (self.classify)(&item))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.input.size_hint()
+	}
+}
+
+pub trait ProcessWith: Iterator + Sized {
+	/// Classify each item as it is pulled, without collecting the outcomes. Combined with
+	/// [`CountOutcomes::count_outcomes`], huge streams can be summarized in constant memory.
+	fn process_with<C: FnMut(&Self::Item) -> Outcome>(self, classify: C) -> ProcessStuff<Self, C> {
+		ProcessStuff {
+			input: self,
+			classify,
+		}
+	}
+}
+
+impl<I: Iterator> ProcessWith for I {}
+
 // What we want to achieve is a quick way (in terms of lines of code) to scan the output and
 // determine how many were okay, how many were error, etc.
 //
 // A boring solution follows 🫣:
 
 pub fn ok_count(outcomes: Vec<Outcome>) -> usize {
-	// todo!();
-    outcomes.iter().filter(|&outcome| outcome.eq(&Outcome::Ok)).count()
+	outcomes.count_of(&Outcome::Ok)
 }
 pub fn something_went_wrong_count(outcomes: Vec<Outcome>) -> usize {
-	// todo!();
-	outcomes.iter().filter(|&outcome| outcome.eq(&Outcome::SomethingWentWrong)).count()
+	outcomes.count_of(&Outcome::SomethingWentWrong)
 }
 pub fn i_dont_know_count(outcomes: Vec<Outcome>) -> usize {
-	// todo!();
-	outcomes.iter().filter(|&outcome| outcome.eq(&Outcome::IDontKnow)).count()
+	outcomes.count_of(&Outcome::IDontKnow)
+}
+
+/// Counts the items equal to some value, in anything that can be iterated, e.g. a slice or an
+/// iterator, which it consumes.
+pub trait CountOf<T: PartialEq> {
+	fn count_of(self, needle: &T) -> usize;
+}
+
+impl<T: PartialEq, I: IntoIterator> CountOf<T> for I
+where
+	I::Item: std::borrow::Borrow<T>,
+{
+	fn count_of(self, needle: &T) -> usize {
+		use std::borrow::Borrow;
+		self.into_iter()
+			.filter(|item| item.borrow() == needle)
+			.count()
+	}
 }
 
 // This is quite lame. We want to be able to call these methods directly on the `Vec<Outcome>`. But
@@ -58,22 +427,704 @@ pub fn i_dont_know_count(outcomes: Vec<Outcome>) -> usize {
 // This is a very common approach, and is called an "extension trait".
 
 pub trait OutcomeCount {
-	fn ok_count(&self) -> usize;
-	fn something_went_wrong_count(&self) -> usize;
-	fn i_dont_know_count(&self) -> usize;
-}
+	/// An iterator over the outcomes of the collection, in order.
+	type Outcomes<'a>: Iterator<Item = Outcome>
+	where
+		Self: 'a;
 
-// First, implement this trait.
+	fn outcomes(&self) -> Self::Outcomes<'_>;
 
-impl OutcomeCount for Vec<Outcome> {
 	fn ok_count(&self) -> usize {
-		self.iter().filter(|&o| o.eq(&Outcome::Ok)).count()
+		self.outcomes().count_of(&Outcome::Ok)
+	}
+	fn something_went_wrong_count(&self) -> usize {
+		self.outcomes().count_of(&Outcome::SomethingWentWrong)
 	}
 	fn i_dont_know_count(&self) -> usize {
-		self.iter().filter(|&o| o.eq(&Outcome::IDontKnow)).count()
+		self.outcomes().count_of(&Outcome::IDontKnow)
 	}
-	fn something_went_wrong_count(&self) -> usize {
-		self.iter().filter(|&o| o.eq(&Outcome::SomethingWentWrong)).count()
+
+	/// All three counts at once, in a single pass.
+	fn counts(&self) -> OutcomeSummary {
+		let mut summary = OutcomeSummary::default();
+		self.outcomes().for_each(|o| summary.record(&o));
+		summary
+	}
+
+	/// A summary per consecutive chunk of `chunk_size` outcomes, e.g. the failure rate per
+	/// thousand items. The last chunk may be shorter.
+	///
+	/// Panics if `chunk_size` is zero.
+	fn summarize_chunks(&self, chunk_size: usize) -> Vec<OutcomeSummary> {
+		assert!(chunk_size > 0, "chunk size must not be zero");
+		let mut summaries = Vec::new();
+		for (index, outcome) in self.outcomes().enumerate() {
+			if index % chunk_size == 0 {
+				summaries.push(OutcomeSummary::default());
+			}
+			summaries.last_mut().unwrap().record(&outcome);
+		}
+		summaries
+	}
+
+	/// The ok, failed and unknown counts, in that order, as a plain array.
+	fn counts_array(&self) -> [usize; 3] {
+		let mut counts = [0; 3];
+		for outcome in self.outcomes() {
+			// The variants are declared in that same order.
+			counts[outcome as usize] += 1;
+		}
+		counts
+	}
+
+	/// How many times each outcome occurs. Outcomes that do not occur at all have no entry.
+	fn count_by(&self) -> std::collections::HashMap<Outcome, usize> {
+		let mut counts = std::collections::HashMap::new();
+		for outcome in self.outcomes() {
+			*counts.entry(outcome).or_insert(0) += 1;
+		}
+		counts
+	}
+
+	/// See [`OutcomeSummary::score`].
+	fn score(&self, weights: OutcomeWeights) -> i64 {
+		self.counts().score(weights)
+	}
+
+	/// See [`OutcomeSummary::success_rate`].
+	fn success_rate(&self) -> f64 {
+		self.counts().success_rate()
+	}
+	/// See [`OutcomeSummary::failure_rate`].
+	fn failure_rate(&self) -> f64 {
+		self.counts().failure_rate()
+	}
+	/// See [`OutcomeSummary::unknown_rate`].
+	fn unknown_rate(&self) -> f64 {
+		self.counts().unknown_rate()
+	}
+
+	/// The length of the longest uninterrupted run of `outcome`.
+	fn longest_streak(&self, outcome: Outcome) -> usize {
+		let (mut longest, mut current) = (0, 0);
+		for o in self.outcomes() {
+			current = if o == outcome { current + 1 } else { 0 };
+			longest = longest.max(current);
+		}
+		longest
+	}
+	fn longest_ok_streak(&self) -> usize {
+		self.longest_streak(Outcome::Ok)
+	}
+	fn longest_failure_streak(&self) -> usize {
+		self.longest_streak(Outcome::SomethingWentWrong)
+	}
+
+	/// The outcomes, run-length encoded: each run of equal outcomes becomes one entry, with its
+	/// length.
+	fn runs(&self) -> Vec<(Outcome, usize)> {
+		let mut runs: Vec<(Outcome, usize)> = Vec::new();
+		for outcome in self.outcomes() {
+			match runs.last_mut() {
+				Some((last, length)) if *last == outcome => *length += 1,
+				_ => runs.push((outcome, 1)),
+			}
+		}
+		runs
+	}
+
+	/// Send a warning about every failed outcome to the sink set with [`set_log_sink`], such that
+	/// failures show up in an existing log pipeline.
+	#[cfg(feature = "tracing")]
+	fn log_failures(&self, target: &str) {
+		self.log_failures_with(target, warn);
+	}
+
+	/// Send a warning about every failed outcome to `sink`, as a target and a message, such that
+	/// failures show up in an existing log pipeline.
+	fn log_failures_with(&self, target: &str, mut sink: impl FnMut(&str, &str)) {
+		for (index, outcome) in self.outcomes().enumerate() {
+			if outcome == Outcome::SomethingWentWrong {
+				sink(target, &format!("outcome #{index}: {outcome}"));
+			}
+		}
+	}
+
+	/// The indices of the ok, failed and unknown outcomes respectively, such that one can go back
+	/// to the inputs that produced them.
+	fn partition_outcomes(&self) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+		let (mut ok, mut failed, mut unknown) = (Vec::new(), Vec::new(), Vec::new());
+		for (index, outcome) in self.outcomes().enumerate() {
+			match outcome {
+				Outcome::Ok => ok.push(index),
+				Outcome::SomethingWentWrong => failed.push(index),
+				Outcome::IDontKnow => unknown.push(index),
+			}
+		}
+		(ok, failed, unknown)
+	}
+
+	/// The most common outcome, e.g. the consensus verdict of redundantly processing the same
+	/// item. `None` if there are no outcomes, or if two outcomes are tied for most common.
+	fn majority(&self) -> Option<Outcome> {
+		let counts = self.counts_array();
+		let most = *counts.iter().max()?;
+		let outcomes = [Outcome::Ok, Outcome::SomethingWentWrong, Outcome::IDontKnow];
+		let mut winners = counts.iter().zip(outcomes).filter(|(c, _)| **c == most);
+		match (winners.next(), winners.next()) {
+			(Some((&c, outcome)), None) if c > 0 => Some(outcome),
+			_ => None,
+		}
+	}
+
+	/// The outcome all outcomes agree on, or `None` if they disagree or there are none.
+	fn unanimous(&self) -> Option<Outcome> {
+		let mut outcomes = self.outcomes();
+		let first = outcomes.next()?;
+		outcomes.all(|o| o == first).then_some(first)
+	}
+}
+
+/// How many outcomes of each kind a collection has.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutcomeSummary {
+	pub ok: usize,
+	pub failed: usize,
+	pub unknown: usize,
+}
+
+impl OutcomeSummary {
+	/// Count one more outcome.
+	pub fn record(&mut self, outcome: &Outcome) {
+		match outcome {
+			Outcome::Ok => self.ok += 1,
+			Outcome::SomethingWentWrong => self.failed += 1,
+			Outcome::IDontKnow => self.unknown += 1,
+		}
+	}
+
+	pub fn total(&self) -> usize {
+		self.ok + self.failed + self.unknown
+	}
+
+	/// The share of ok outcomes, between 0.0 and 1.0. Zero if there are no outcomes at all.
+	pub fn success_rate(&self) -> f64 {
+		self.rate(self.ok)
+	}
+
+	/// The share of failed outcomes, between 0.0 and 1.0. Zero if there are no outcomes at all.
+	pub fn failure_rate(&self) -> f64 {
+		self.rate(self.failed)
+	}
+
+	/// The share of unknown outcomes, between 0.0 and 1.0. Zero if there are no outcomes at all.
+	pub fn unknown_rate(&self) -> f64 {
+		self.rate(self.unknown)
+	}
+
+	/// The status a batch processing binary should exit with: 0 if nothing failed, 1 otherwise.
+	/// Unknown outcomes are not failures.
+	pub fn exit_code(&self) -> u8 {
+		if self.failed == 0 {
+			0
+		} else {
+			1
+		}
+	}
+
+	/// Reduce the summary to a single number, by adding up the weight of every outcome.
+	pub fn score(&self, weights: OutcomeWeights) -> i64 {
+		self.ok as i64 * weights.ok
+			+ self.failed as i64 * weights.failed
+			+ self.unknown as i64 * weights.unknown
+	}
+
+	/// The counts in the Prometheus text exposition format, as `<prefix>_ok_total`,
+	/// `<prefix>_failed_total` and `<prefix>_unknown_total` counters.
+	pub fn to_prometheus(&self, prefix: &str) -> String {
+		use std::fmt::Write;
+
+		let mut text = String::new();
+		for (name, count) in [
+			("ok", self.ok),
+			("failed", self.failed),
+			("unknown", self.unknown),
+		] {
+			let metric = format!("{prefix}_{name}_total");
+			writeln!(text, "# TYPE {metric} counter").unwrap();
+			writeln!(text, "{metric} {count}").unwrap();
+		}
+		text
+	}
+
+	fn rate(&self, count: usize) -> f64 {
+		match self.total() {
+			0 => 0.0,
+			total => count as f64 / total as f64,
+		}
+	}
+}
+
+impl crate::csv::ToCsv for [OutcomeSummary] {
+	/// One line per summary, e.g. per chunk of [`OutcomeCount::summarize_chunks`].
+	fn to_csv(&self, mut out: impl std::io::Write) -> std::io::Result<()> {
+		writeln!(out, "index,ok,failed,unknown")?;
+		for (index, summary) in self.iter().enumerate() {
+			let OutcomeSummary {
+				ok,
+				failed,
+				unknown,
+			} = summary;
+			writeln!(out, "{index},{ok},{failed},{unknown}")?;
+		}
+		out.flush()
+	}
+}
+
+/// How much each kind of outcome is worth, see [`OutcomeSummary::score`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutcomeWeights {
+	pub ok: i64,
+	pub failed: i64,
+	pub unknown: i64,
+}
+
+/// One point per ok outcome, minus five per failure.
+impl Default for OutcomeWeights {
+	fn default() -> Self {
+		Self {
+			ok: 1,
+			failed: -5,
+			unknown: 0,
+		}
+	}
+}
+
+/// Allows returning a summary from `main`, which exits with [`OutcomeSummary::exit_code`].
+impl std::process::Termination for OutcomeSummary {
+	fn report(self) -> std::process::ExitCode {
+		std::process::ExitCode::from(self.exit_code())
+	}
+}
+
+impl std::ops::Add for OutcomeSummary {
+	type Output = Self;
+
+	fn add(mut self, other: Self) -> Self {
+		self += other;
+		self
+	}
+}
+
+impl std::ops::AddAssign for OutcomeSummary {
+	fn add_assign(&mut self, other: Self) {
+		self.ok += other.ok;
+		self.failed += other.failed;
+		self.unknown += other.unknown;
+	}
+}
+
+impl std::iter::Sum for OutcomeSummary {
+	fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+		iter.fold(Self::default(), |total, summary| total + summary)
+	}
+}
+
+impl<'a> std::iter::Sum<&'a OutcomeSummary> for OutcomeSummary {
+	fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+		iter.copied().sum()
+	}
+}
+
+impl std::fmt::Display for OutcomeSummary {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} ok, {} failed, {} unknown ({} total)",
+			self.ok,
+			self.failed,
+			self.unknown,
+			self.total()
+		)
+	}
+}
+
+// First, implement this trait.
+//
+// It is implemented for slices of anything that is `AsOutcome`, so outcomes carrying payloads can
+// be counted as well. Thanks to auto-deref, this covers arrays and boxed slices too, and `Vec`s
+// get an implementation of their own.
+
+impl<O: AsOutcome> OutcomeCount for [O] {
+	type Outcomes<'a>
+		= core::iter::Map<core::slice::Iter<'a, O>, fn(&O) -> Outcome>
+	where
+		O: 'a;
+
+	fn outcomes(&self) -> Self::Outcomes<'_> {
+		self.iter().map(O::as_outcome)
+	}
+}
+
+impl<O: AsOutcome> OutcomeCount for Vec<O> {
+	type Outcomes<'a>
+		= <[O] as OutcomeCount>::Outcomes<'a>
+	where
+		O: 'a;
+
+	fn outcomes(&self) -> Self::Outcomes<'_> {
+		self.as_slice().outcomes()
+	}
+}
+
+impl<O: AsOutcome> OutcomeCount for std::collections::VecDeque<O> {
+	type Outcomes<'a>
+		= core::iter::Map<std::collections::vec_deque::Iter<'a, O>, fn(&O) -> Outcome>
+	where
+		O: 'a;
+
+	fn outcomes(&self) -> Self::Outcomes<'_> {
+		self.iter().map(O::as_outcome)
+	}
+}
+
+/// Bulk, in-place transformations of outcomes, e.g. to apply post-processing policies.
+pub trait MapOutcomes {
+	/// Replace every outcome by `f` of it.
+	fn map_outcomes(&mut self, f: impl FnMut(Outcome) -> Outcome);
+
+	/// Treat every unknown outcome as a failure.
+	fn reclassify_unknown_as_failed(&mut self) {
+		self.map_outcomes(|outcome| match outcome {
+			Outcome::IDontKnow => Outcome::SomethingWentWrong,
+			other => other,
+		});
+	}
+}
+
+impl MapOutcomes for [Outcome] {
+	fn map_outcomes(&mut self, mut f: impl FnMut(Outcome) -> Outcome) {
+		self.iter_mut().for_each(|outcome| *outcome = f(*outcome));
+	}
+}
+
+/// Sorts items into domain specific categories, for when three kinds of outcomes are not enough.
+pub trait Categorize {
+	type Category: Eq + std::hash::Hash;
+
+	fn category(&self) -> Self::Category;
+}
+
+impl Categorize for Outcome {
+	type Category = Outcome;
+
+	fn category(&self) -> Outcome {
+		*self
+	}
+}
+
+impl<T, E> Categorize for DetailedOutcome<T, E> {
+	type Category = Outcome;
+
+	fn category(&self) -> Outcome {
+		self.as_outcome()
+	}
+}
+
+pub trait CountCategories<C: Categorize> {
+	/// How many items fall in each category. Empty categories have no entry.
+	fn count_categories(&self) -> std::collections::HashMap<C::Category, usize>;
+}
+
+impl<C: Categorize> CountCategories<C> for [C] {
+	fn count_categories(&self) -> std::collections::HashMap<C::Category, usize> {
+		let mut counts = std::collections::HashMap::new();
+		for item in self {
+			*counts.entry(item.category()).or_insert(0) += 1;
+		}
+		counts
+	}
+}
+
+/// In-place cleanup of result vectors.
+pub trait OutcomeOps<O> {
+	/// Keep only the ok outcomes.
+	fn retain_ok(&mut self);
+	/// Remove the failed outcomes and return them, in order.
+	fn drain_failures(&mut self) -> Vec<O>;
+	/// The index of the first failed outcome, if any.
+	fn first_failure(&self) -> Option<usize>;
+}
+
+impl<O: AsOutcome> OutcomeOps<O> for Vec<O> {
+	fn retain_ok(&mut self) {
+		self.retain(|o| o.as_outcome() == Outcome::Ok);
+	}
+
+	fn drain_failures(&mut self) -> Vec<O> {
+		let (failures, rest) = std::mem::take(self)
+			.into_iter()
+			.partition(|o| o.as_outcome() == Outcome::SomethingWentWrong);
+		*self = rest;
+		failures
+	}
+
+	fn first_failure(&self) -> Option<usize> {
+		self.iter()
+			.position(|o| o.as_outcome() == Outcome::SomethingWentWrong)
+	}
+}
+
+/// A list of outcomes packed into 2 bits each, for very large result sets.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactOutcomes {
+	bits: Vec<u8>,
+	len: usize,
+}
+
+impl CompactOutcomes {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// The number of bytes used to store the outcomes.
+	pub fn storage_bytes(&self) -> usize {
+		self.bits.len()
+	}
+
+	pub fn push(&mut self, outcome: Outcome) {
+		let code = match outcome {
+			Outcome::Ok => 0,
+			Outcome::SomethingWentWrong => 1,
+			Outcome::IDontKnow => 2,
+		};
+		if self.len % 4 == 0 {
+			self.bits.push(0);
+		}
+		self.bits[self.len / 4] |= code << (self.len % 4 * 2);
+		self.len += 1;
+	}
+
+	pub fn get(&self, index: usize) -> Option<Outcome> {
+		if index >= self.len {
+			return None;
+		}
+		match (self.bits[index / 4] >> (index % 4 * 2)) & 0b11 {
+			0 => Some(Outcome::Ok),
+			1 => Some(Outcome::SomethingWentWrong),
+			_ => Some(Outcome::IDontKnow),
+		}
+	}
+
+	pub fn iter(&self) -> CompactOutcomesIter<'_> {
+		CompactOutcomesIter {
+			outcomes: self,
+			index: 0,
+		}
+	}
+}
+
+impl FromIterator<Outcome> for CompactOutcomes {
+	fn from_iter<I: IntoIterator<Item = Outcome>>(iter: I) -> Self {
+		let mut outcomes = Self::new();
+		outcomes.extend(iter);
+		outcomes
+	}
+}
+
+impl Extend<Outcome> for CompactOutcomes {
+	fn extend<I: IntoIterator<Item = Outcome>>(&mut self, iter: I) {
+		iter.into_iter().for_each(|outcome| self.push(outcome));
+	}
+}
+
+/// The iterator returned by [`CompactOutcomes::iter`].
+#[derive(Clone)]
+pub struct CompactOutcomesIter<'a> {
+	outcomes: &'a CompactOutcomes,
+	index: usize,
+}
+
+impl Iterator for CompactOutcomesIter<'_> {
+	type Item = Outcome;
+
+	fn next(&mut self) -> Option<Outcome> {
+		let outcome = self.outcomes.get(self.index)?;
+		self.index += 1;
+		Some(outcome)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = self.outcomes.len() - self.index;
+		(remaining, Some(remaining))
+	}
+}
+
+impl OutcomeCount for CompactOutcomes {
+	type Outcomes<'a> = CompactOutcomesIter<'a>;
+
+	fn outcomes(&self) -> Self::Outcomes<'_> {
+		self.iter()
+	}
+}
+
+/// An outcome, along with when it happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimedOutcome {
+	pub outcome: Outcome,
+	pub at: std::time::Instant,
+}
+
+impl TimedOutcome {
+	/// An outcome that happened just now.
+	pub fn now(outcome: Outcome) -> Self {
+		Self {
+			outcome,
+			at: std::time::Instant::now(),
+		}
+	}
+}
+
+impl AsOutcome for TimedOutcome {
+	fn as_outcome(&self) -> Outcome {
+		self.outcome
+	}
+}
+
+/// Failure statistics over time, for timed outcomes in chronological order.
+pub trait TimedOutcomeStats {
+	/// The number of failures divided by the time between the first and last outcome. `None` if
+	/// that time is zero.
+	fn failures_per_second(&self) -> Option<f64>;
+
+	/// The average time between two consecutive failures. `None` if there are less than two
+	/// failures.
+	fn mean_time_between_failures(&self) -> Option<std::time::Duration>;
+}
+
+impl TimedOutcomeStats for [TimedOutcome] {
+	fn failures_per_second(&self) -> Option<f64> {
+		let span = self
+			.last()?
+			.at
+			.duration_since(self.first()?.at)
+			.as_secs_f64();
+		if span == 0.0 {
+			return None;
+		}
+		Some(self.something_went_wrong_count() as f64 / span)
+	}
+
+	fn mean_time_between_failures(&self) -> Option<std::time::Duration> {
+		let mut failures = self
+			.iter()
+			.filter(|o| o.outcome == Outcome::SomethingWentWrong);
+		let first = failures.next()?.at;
+		let (last, gaps) = failures.fold((first, 0u32), |(_, gaps), o| (o.at, gaps + 1));
+		if gaps == 0 {
+			return None;
+		}
+		Some(last.duration_since(first) / gaps)
+	}
+}
+
+/// Keeps the most recent outcomes only, such that long-running processors can monitor their
+/// recent failure rate rather than their lifetime totals.
+#[derive(Clone, Debug)]
+pub struct OutcomeWindow {
+	recent: std::collections::VecDeque<Outcome>,
+	capacity: usize,
+	summary: OutcomeSummary,
+}
+
+impl OutcomeWindow {
+	/// A window over the last `capacity` outcomes, which must not be zero.
+	pub fn new(capacity: usize) -> Self {
+		assert!(
+			capacity > 0,
+			"a window must have room for at least one outcome"
+		);
+		Self {
+			recent: std::collections::VecDeque::with_capacity(capacity),
+			capacity,
+			summary: OutcomeSummary::default(),
+		}
+	}
+
+	/// Add an outcome, forgetting the oldest one if the window is full.
+	pub fn push(&mut self, outcome: Outcome) {
+		if self.recent.len() == self.capacity {
+			match self.recent.pop_front() {
+				Some(Outcome::Ok) => self.summary.ok -= 1,
+				Some(Outcome::SomethingWentWrong) => self.summary.failed -= 1,
+				Some(Outcome::IDontKnow) => self.summary.unknown -= 1,
+				None => {}
+			}
+		}
+		self.recent.push_back(outcome);
+		self.summary.record(&outcome);
+	}
+
+	/// The counts of the outcomes currently in the window.
+	pub fn summary(&self) -> OutcomeSummary {
+		self.summary
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+}
+
+/// Summarizes outcomes as they stream by, e.g. out of a channel, without collecting them first.
+pub trait CountOutcomes: Iterator {
+	fn count_outcomes(self) -> OutcomeSummary;
+}
+
+impl<I: Iterator> CountOutcomes for I
+where
+	I::Item: AsOutcome,
+{
+	fn count_outcomes(self) -> OutcomeSummary {
+		let mut summary = OutcomeSummary::default();
+		self.for_each(|o| summary.record(&o.as_outcome()));
+		summary
+	}
+}
+
+/// Counts outcomes on several threads, for very large result sets.
+pub trait ParOutcomeCount {
+	/// Counts on all available threads.
+	fn par_counts(&self) -> OutcomeSummary {
+		let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+		self.counts_parallel(threads)
+	}
+
+	/// Counts on (at most) `threads` scoped threads, one per chunk, and sums the summaries.
+	///
+	/// Panics if `threads` is zero.
+	fn counts_parallel(&self, threads: usize) -> OutcomeSummary;
+}
+
+impl<O: AsOutcome + Sync> ParOutcomeCount for [O] {
+	fn counts_parallel(&self, threads: usize) -> OutcomeSummary {
+		assert!(threads > 0, "need at least one thread");
+		let chunk_size = ((self.len() + threads - 1) / threads).max(1);
+
+		std::thread::scope(|s| {
+			let handles = self
+				.chunks(chunk_size)
+				.map(|chunk| s.spawn(move || chunk.counts()))
+				.collect::<Vec<_>>();
+			handles.into_iter().map(|h| h.join().unwrap()).sum()
+		})
 	}
 }
 
@@ -113,4 +1164,705 @@ mod tests {
 		assert_eq!(x.i_dont_know_count(), 1);
 		assert_eq!(x.something_went_wrong_count(), 0);
 	}
+
+	#[test]
+	fn detailed_outcomes() {
+		let x: Vec<DetailedOutcome<u32, &str>> = vec![
+			DetailedOutcome::Ok(4),
+			DetailedOutcome::Failed("three"),
+			DetailedOutcome::Unknown,
+			DetailedOutcome::Ok(2),
+		];
+
+		assert_eq!(x.ok_count(), 2);
+		assert_eq!(x.something_went_wrong_count(), 1);
+		assert_eq!(x.i_dont_know_count(), 1);
+
+		assert_eq!(
+			Outcome::from(DetailedOutcome::<u32, &str>::Failed("three")),
+			Outcome::SomethingWentWrong
+		);
+		assert_eq!(
+			DetailedOutcome::from(Outcome::IDontKnow),
+			DetailedOutcome::Unknown
+		);
+		assert_eq!(DetailedOutcome::from(Outcome::Ok), DetailedOutcome::Ok(()));
+	}
+
+	#[test]
+	fn other_collections() {
+		let x = [
+			Outcome::Ok,
+			Outcome::SomethingWentWrong,
+			Outcome::IDontKnow,
+			Outcome::Ok,
+		];
+		assert_eq!(x.ok_count(), 2);
+
+		let slice: &[Outcome] = &x[1..];
+		assert_eq!(slice.ok_count(), 1);
+		assert_eq!(slice.something_went_wrong_count(), 1);
+
+		let boxed: Box<[Outcome]> = Box::new(x);
+		assert_eq!(boxed.i_dont_know_count(), 1);
+
+		let deque: std::collections::VecDeque<Outcome> = x.into_iter().collect();
+		assert_eq!(deque.ok_count(), 2);
+		assert_eq!(deque.something_went_wrong_count(), 1);
+		assert_eq!(deque.i_dont_know_count(), 1);
+	}
+
+	#[test]
+	fn summary() {
+		let x = [
+			Outcome::Ok,
+			Outcome::Ok,
+			Outcome::IDontKnow,
+			Outcome::SomethingWentWrong,
+		];
+
+		let summary = x.counts();
+		assert_eq!(
+			summary,
+			OutcomeSummary {
+				ok: 2,
+				failed: 1,
+				unknown: 1
+			}
+		);
+		assert_eq!(summary.total(), 4);
+		assert_eq!(summary.to_string(), "2 ok, 1 failed, 1 unknown (4 total)");
+		assert_eq!(Vec::<Outcome>::new().counts(), OutcomeSummary::default());
+	}
+
+	#[test]
+	fn streaming_counts() {
+		let x = vec![Outcome::Ok, Outcome::IDontKnow, Outcome::SomethingWentWrong];
+		assert_eq!(x.iter().count_outcomes(), x.counts());
+		assert_eq!(x.clone().into_iter().count_outcomes(), x.counts());
+
+		let (sender, receiver) = std::sync::mpsc::channel();
+		std::thread::spawn(move || {
+			for i in 0..10u32 {
+				sender
+					.send(process_stuff(std::iter::once(i)).remove(0))
+					.unwrap();
+			}
+		});
+		assert_eq!(
+			receiver.into_iter().count_outcomes(),
+			OutcomeSummary {
+				ok: 5,
+				failed: 1,
+				unknown: 4
+			}
+		);
+	}
+
+	#[test]
+	fn parallel_counts() {
+		let x = process_stuff(0..100_000u32);
+		assert_eq!(x.par_counts(), x.counts());
+		assert_eq!(x[..3].par_counts(), x[..3].counts());
+		assert_eq!(
+			Vec::<Outcome>::new().par_counts(),
+			OutcomeSummary::default()
+		);
+	}
+
+	#[test]
+	fn results_to_outcomes() {
+		assert_eq!(Outcome::from(Ok::<u8, ()>(1)), Outcome::Ok);
+		assert_eq!(
+			Outcome::from(Err::<(), _>("nope")),
+			Outcome::SomethingWentWrong
+		);
+		assert_eq!(
+			DetailedOutcome::from(Err::<(), _>("nope")),
+			DetailedOutcome::Failed("nope")
+		);
+
+		let outcomes = ["1", "two", "3"]
+			.iter()
+			.map(|s| s.parse::<u32>())
+			.map_results_to_outcomes()
+			.collect::<Vec<_>>();
+		assert_eq!(outcomes.ok_count(), 2);
+		assert_eq!(outcomes.something_went_wrong_count(), 1);
+	}
+
+	#[test]
+	fn outcome_display_and_hash() {
+		assert_eq!(Outcome::Ok.to_string(), "ok");
+		assert_eq!(
+			Outcome::SomethingWentWrong.to_string(),
+			"something went wrong"
+		);
+		assert_eq!(Outcome::IDontKnow.to_string(), "i don't know");
+
+		let mut seen = std::collections::HashMap::new();
+		for outcome in process_stuff(0..10u32) {
+			*seen.entry(outcome).or_insert(0) += 1;
+		}
+		assert_eq!(seen[&Outcome::Ok], 5);
+		assert_eq!(seen[&Outcome::SomethingWentWrong], 1);
+	}
+
+	#[test]
+	fn partition_by_variant() {
+		let inputs = [4u32, 3, 5, 8, 3];
+		let outcomes = process_stuff(inputs.iter().copied());
+
+		let (ok, failed, unknown) = outcomes.partition_outcomes();
+		assert_eq!(ok, vec![0, 3]);
+		assert_eq!(failed, vec![1, 4]);
+		assert_eq!(unknown, vec![2]);
+		assert_eq!(
+			failed.iter().map(|&i| inputs[i]).collect::<Vec<_>>(),
+			vec![3, 3]
+		);
+	}
+
+	#[test]
+	fn in_place_cleanup() {
+		let mut x = process_stuff(1..7u32);
+		assert_eq!(x.first_failure(), Some(2));
+		assert_eq!(x.drain_failures(), vec![Outcome::SomethingWentWrong]);
+		assert_eq!(x.first_failure(), None);
+		assert_eq!(x.len(), 5);
+
+		x.retain_ok();
+		assert_eq!(x, vec![Outcome::Ok; 3]);
+
+		let mut detailed = vec![DetailedOutcome::Failed("a"), DetailedOutcome::Ok(1)];
+		assert_eq!(
+			detailed.drain_failures(),
+			vec![DetailedOutcome::Failed("a")]
+		);
+		assert_eq!(detailed, vec![DetailedOutcome::Ok(1)]);
+	}
+
+	#[test]
+	fn custom_classifier() {
+		let outcomes = process_stuff_with(["ok", "", "??"].into_iter(), |s| match *s {
+			"" => Outcome::SomethingWentWrong,
+			"??" => Outcome::IDontKnow,
+			_ => Outcome::Ok,
+		});
+		assert_eq!(
+			outcomes,
+			vec![Outcome::Ok, Outcome::SomethingWentWrong, Outcome::IDontKnow]
+		);
+
+		let numeric = process_stuff_with(0..10u32, |&n| process_stuff(std::iter::once(n))[0]);
+		assert_eq!(numeric, process_stuff(0..10u32));
+	}
+
+	#[test]
+	fn lazy_processing() {
+		let mut classified = 0;
+		let summary = (0..1_000_000u64)
+			.process_with(|n| {
+				classified += 1;
+				if n % 2 == 0 {
+					Outcome::Ok
+				} else {
+					Outcome::IDontKnow
+				}
+			})
+			.take(10)
+			.count_outcomes();
+
+		assert_eq!(
+			summary,
+			OutcomeSummary {
+				ok: 5,
+				failed: 0,
+				unknown: 5
+			}
+		);
+		assert_eq!(classified, 10);
+	}
+
+	#[test]
+	fn merge_summaries() {
+		let x = process_stuff(0..1000u32);
+		let chunk_summaries = x
+			.chunks(100)
+			.map(|chunk| chunk.counts())
+			.collect::<Vec<_>>();
+
+		assert_eq!(chunk_summaries.iter().sum::<OutcomeSummary>(), x.counts());
+		assert_eq!(
+			chunk_summaries.into_iter().sum::<OutcomeSummary>(),
+			x.counts()
+		);
+
+		let mut total = x[..10].counts();
+		total += x[10..].counts();
+		assert_eq!(total, x.counts());
+		assert_eq!(x[..1].counts() + x[1..].counts(), x.counts());
+	}
+
+	#[test]
+	fn rates() {
+		let x = process_stuff(0..8u32);
+		assert_eq!(x.success_rate(), 0.5);
+		assert_eq!(x.failure_rate(), 0.125);
+		assert_eq!(x.unknown_rate(), 0.375);
+		assert_eq!(x.counts().success_rate(), 0.5);
+
+		let empty = Vec::<Outcome>::new();
+		assert_eq!(empty.success_rate(), 0.0);
+		assert_eq!(empty.failure_rate(), 0.0);
+		assert_eq!(OutcomeSummary::default().unknown_rate(), 0.0);
+	}
+
+	#[test]
+	fn count_by_outcome() {
+		let counts = process_stuff([2u32, 4, 5].into_iter()).count_by();
+		assert_eq!(counts.len(), 2);
+		assert_eq!(counts[&Outcome::Ok], 2);
+		assert_eq!(counts[&Outcome::IDontKnow], 1);
+		assert_eq!(counts.get(&Outcome::SomethingWentWrong), None);
+	}
+
+	#[test]
+	fn count_of_anything() {
+		let words = ["a", "b", "a", "c"];
+		assert_eq!(words.count_of(&"a"), 2);
+		assert_eq!(words[1..].count_of(&"a"), 1);
+		assert_eq!(words.iter().count_of(&&"c"), 1);
+		assert_eq!((0..10).map(|n| n % 3).count_of(&0), 4);
+
+		let outcomes = process_stuff(0..10u32);
+		assert_eq!(outcomes.iter().count_of(&Outcome::Ok), outcomes.ok_count());
+		assert_eq!(ok_count(outcomes.clone()), outcomes.ok_count());
+
+		// The outcomes of a collection need not be cloneable to be counted.
+		struct Boxed(Vec<Outcome>);
+		impl OutcomeCount for Boxed {
+			type Outcomes<'a> = Box<dyn Iterator<Item = Outcome> + 'a>;
+
+			fn outcomes(&self) -> Self::Outcomes<'_> {
+				Box::new(self.0.iter().copied())
+			}
+		}
+		assert_eq!(Boxed(outcomes.clone()).ok_count(), outcomes.ok_count());
+	}
+
+	#[test]
+	fn early_return() {
+		fn all_ok(outcomes: &[Outcome]) -> Result<usize, OutcomeError> {
+			for outcome in outcomes {
+				outcome.ok_or()?;
+			}
+			Ok(outcomes.len())
+		}
+
+		assert_eq!(all_ok(&[Outcome::Ok, Outcome::Ok]), Ok(2));
+		assert_eq!(
+			all_ok(&process_stuff(0..4u32)),
+			Err(OutcomeError::IDontKnow)
+		);
+		assert_eq!(
+			Result::from(Outcome::SomethingWentWrong),
+			Err(OutcomeError::SomethingWentWrong)
+		);
+		assert_eq!(
+			OutcomeError::IDontKnow.to_string(),
+			"the outcome is unknown"
+		);
+	}
+
+	#[test]
+	fn exit_codes() {
+		assert_eq!(
+			process_stuff([2u32, 4, 5].into_iter()).counts().exit_code(),
+			0
+		);
+		assert_eq!(process_stuff(0..4u32).counts().exit_code(), 1);
+		assert_eq!(OutcomeSummary::default().exit_code(), 0);
+	}
+
+	#[test]
+	fn second_pass() {
+		let items = [1u32, 2, 3, 5, 7];
+		let mut outcomes = process_stuff(items.iter().copied());
+
+		let mut retried = Vec::new();
+		retry_unknowns(&items, &mut outcomes, |&n| {
+			retried.push(n);
+			if n > 4 {
+				Outcome::Ok
+			} else {
+				Outcome::IDontKnow
+			}
+		});
+
+		assert_eq!(retried, vec![1, 5, 7]);
+		assert_eq!(
+			outcomes,
+			vec![
+				Outcome::IDontKnow,
+				Outcome::Ok,
+				Outcome::SomethingWentWrong,
+				Outcome::Ok,
+				Outcome::Ok
+			]
+		);
+	}
+
+	#[test]
+	fn weighted_score() {
+		let x = process_stuff(0..10u32);
+		assert_eq!(x.score(OutcomeWeights::default()), 5 - 5);
+		assert_eq!(
+			x.score(OutcomeWeights {
+				ok: 2,
+				failed: -1,
+				unknown: -1
+			}),
+			10 - 1 - 4
+		);
+		assert_eq!(Vec::<Outcome>::new().score(OutcomeWeights::default()), 0);
+	}
+
+	#[test]
+	fn prometheus_export() {
+		let summary = OutcomeSummary {
+			ok: 3,
+			failed: 1,
+			unknown: 0,
+		};
+		assert_eq!(
+			summary.to_prometheus("jobs"),
+			"# TYPE jobs_ok_total counter\n\
+			 jobs_ok_total 3\n\
+			 # TYPE jobs_failed_total counter\n\
+			 jobs_failed_total 1\n\
+			 # TYPE jobs_unknown_total counter\n\
+			 jobs_unknown_total 0\n"
+		);
+	}
+
+	#[test]
+	fn custom_categories() {
+		struct Response(u16);
+		impl Categorize for Response {
+			type Category = u16;
+
+			fn category(&self) -> u16 {
+				self.0 / 100
+			}
+		}
+
+		let responses = [Response(200), Response(404), Response(204), Response(500)];
+		let counts = responses.count_categories();
+		assert_eq!(counts[&2], 2);
+		assert_eq!(counts[&4], 1);
+		assert_eq!(counts[&5], 1);
+
+		let outcomes = process_stuff(0..10u32);
+		assert_eq!(outcomes.count_categories(), outcomes.count_by());
+	}
+
+	#[test]
+	fn compact_outcomes() {
+		let mut compact = CompactOutcomes::new();
+		assert!(compact.is_empty());
+		compact.push(Outcome::IDontKnow);
+		compact.push(Outcome::Ok);
+		compact.push(Outcome::SomethingWentWrong);
+		assert_eq!(compact.len(), 3);
+		assert_eq!(compact.get(0), Some(Outcome::IDontKnow));
+		assert_eq!(compact.get(2), Some(Outcome::SomethingWentWrong));
+		assert_eq!(compact.get(3), None);
+
+		let outcomes = process_stuff(0..1_000_003u32);
+		let compact = outcomes.iter().copied().collect::<CompactOutcomes>();
+		assert_eq!(compact.counts(), outcomes.counts());
+		assert_eq!(compact.ok_count(), outcomes.ok_count());
+		assert!(compact.iter().eq(outcomes.iter().copied()));
+
+		// a quarter of a byte per outcome, against one byte each in a `Vec<Outcome>`.
+		assert_eq!(compact.storage_bytes(), 250_001);
+		assert_eq!(std::mem::size_of_val(outcomes.as_slice()), 1_000_003);
+	}
+
+	#[test]
+	fn sliding_window() {
+		let mut window = OutcomeWindow::new(4);
+		assert_eq!(window.summary(), OutcomeSummary::default());
+
+		for outcome in process_stuff(0..10u32) {
+			window.push(outcome);
+		}
+		// the last four are 6, 7, 8 and 9.
+		assert_eq!(
+			window.summary(),
+			OutcomeSummary {
+				ok: 2,
+				failed: 0,
+				unknown: 2
+			}
+		);
+		assert_eq!(window.summary().total(), window.capacity());
+
+		window.push(Outcome::SomethingWentWrong);
+		assert_eq!(window.summary().failure_rate(), 0.25);
+	}
+
+	#[test]
+	fn streaks_and_runs() {
+		use Outcome::*;
+		let x = [
+			Ok,
+			Ok,
+			SomethingWentWrong,
+			SomethingWentWrong,
+			SomethingWentWrong,
+			Ok,
+			IDontKnow,
+		];
+
+		assert_eq!(x.longest_ok_streak(), 2);
+		assert_eq!(x.longest_failure_streak(), 3);
+		assert_eq!(x.longest_streak(IDontKnow), 1);
+		assert_eq!(
+			x.runs(),
+			vec![(Ok, 2), (SomethingWentWrong, 3), (Ok, 1), (IDontKnow, 1)]
+		);
+
+		assert_eq!(Vec::<Outcome>::new().runs(), vec![]);
+		assert_eq!(Vec::<Outcome>::new().longest_ok_streak(), 0);
+	}
+
+	#[test]
+	fn bulk_transformations() {
+		let mut x = process_stuff(0..4u32);
+		x.reclassify_unknown_as_failed();
+		assert_eq!(
+			x.counts(),
+			OutcomeSummary {
+				ok: 2,
+				failed: 2,
+				unknown: 0
+			}
+		);
+
+		x.map_outcomes(|outcome| match outcome {
+			Outcome::Ok => Outcome::IDontKnow,
+			other => other,
+		});
+		assert_eq!(
+			x,
+			vec![
+				Outcome::IDontKnow,
+				Outcome::SomethingWentWrong,
+				Outcome::IDontKnow,
+				Outcome::SomethingWentWrong
+			]
+		);
+	}
+
+	#[test]
+	fn classify_any_type() {
+		struct Job(&'static str);
+		impl Classify for Job {
+			fn classify(&self) -> Outcome {
+				match self.0 {
+					"done" => Outcome::Ok,
+					"crashed" => Outcome::SomethingWentWrong,
+					_ => Outcome::IDontKnow,
+				}
+			}
+		}
+
+		let jobs = [Job("done"), Job("crashed"), Job("queued")];
+		assert_eq!(
+			process_stuff(jobs.iter()),
+			vec![Outcome::Ok, Outcome::SomethingWentWrong, Outcome::IDontKnow]
+		);
+
+		assert_eq!(
+			process_stuff([-4i8, -3, 3].into_iter()),
+			process_stuff([4u64, 5, 3].into_iter())
+		);
+		assert_eq!(
+			process_stuff([2u32, 3, 5].iter()),
+			process_stuff(2..4u32)
+				.into_iter()
+				.chain([Outcome::IDontKnow])
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn counts_as_array() {
+		let x = process_stuff(0..10u32);
+		let summary = x.counts();
+		assert_eq!(
+			x.counts_array(),
+			[summary.ok, summary.failed, summary.unknown]
+		);
+		assert_eq!(CompactOutcomes::new().counts_array(), [0; 3]);
+	}
+
+	#[test]
+	fn chunked_summaries() {
+		let x = process_stuff(0..10u32);
+		let chunks = x.summarize_chunks(4);
+
+		assert_eq!(chunks.len(), 3);
+		assert_eq!(
+			chunks[0],
+			OutcomeSummary {
+				ok: 2,
+				failed: 1,
+				unknown: 1
+			}
+		);
+		assert_eq!(
+			chunks[2],
+			OutcomeSummary {
+				ok: 1,
+				failed: 0,
+				unknown: 1
+			}
+		);
+		assert_eq!(chunks.iter().sum::<OutcomeSummary>(), x.counts());
+		assert_eq!(x.summarize_chunks(100), vec![x.counts()]);
+		assert!(Vec::<Outcome>::new().summarize_chunks(3).is_empty());
+	}
+
+	#[test]
+	fn timed_statistics() {
+		use std::time::Duration;
+
+		let start = std::time::Instant::now();
+		let at = |seconds: u64, outcome| TimedOutcome {
+			outcome,
+			at: start + Duration::from_secs(seconds),
+		};
+		let x = [
+			at(0, Outcome::Ok),
+			at(2, Outcome::SomethingWentWrong),
+			at(3, Outcome::IDontKnow),
+			at(5, Outcome::SomethingWentWrong),
+			at(10, Outcome::SomethingWentWrong),
+		];
+
+		assert_eq!(x.failures_per_second(), Some(0.3));
+		assert_eq!(x.mean_time_between_failures(), Some(Duration::from_secs(4)));
+		assert_eq!(
+			x.counts(),
+			OutcomeSummary {
+				ok: 1,
+				failed: 3,
+				unknown: 1
+			}
+		);
+
+		assert_eq!(x[..2].mean_time_between_failures(), None);
+		assert_eq!(x[..1].failures_per_second(), None);
+		assert_eq!(TimedOutcome::now(Outcome::Ok).outcome, Outcome::Ok);
+	}
+
+	#[test]
+	fn log_failures() {
+		let x = [
+			Outcome::SomethingWentWrong,
+			Outcome::Ok,
+			Outcome::SomethingWentWrong,
+		];
+
+		let mut logged = Vec::new();
+		x.log_failures_with("batch", |target, message| {
+			logged.push(format!("{target} {message}"))
+		});
+		assert_eq!(
+			logged,
+			vec![
+				"batch outcome #0: something went wrong",
+				"batch outcome #2: something went wrong"
+			]
+		);
+	}
+
+	#[cfg(feature = "tracing")]
+	#[test]
+	fn log_failures_to_the_sink() {
+		static LOGGED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+		set_log_sink(|target, message| LOGGED.lock().unwrap().push(format!("{target} {message}")));
+
+		[Outcome::Ok, Outcome::SomethingWentWrong].log_failures("batch");
+		assert_eq!(
+			*LOGGED.lock().unwrap(),
+			vec!["batch outcome #1: something went wrong"]
+		);
+	}
+
+	#[test]
+	fn std_error_handling() {
+		use std::error::Error;
+
+		assert_eq!(ensure_all_ok(&[Outcome::Ok, Outcome::IDontKnow]), Ok(()));
+
+		let error = ensure_all_ok(&process_stuff([3u32, 4, 3].into_iter())).unwrap_err();
+		assert_eq!(
+			error,
+			BatchError {
+				failed: vec![0, 2],
+				total: 3
+			}
+		);
+		assert_eq!(error.to_string(), "2 of 3 items went wrong, at #0 #2");
+
+		let error = ProcessingError::from(error);
+		assert_eq!(
+			error.source().unwrap().to_string(),
+			"2 of 3 items went wrong, at #0 #2"
+		);
+
+		let result: Result<(), ProcessingError> = Outcome::IDontKnow.into();
+		assert_eq!(result, Err(ProcessingError::Item(OutcomeError::IDontKnow)));
+		let result: Result<(), ProcessingError> = Outcome::Ok.into();
+		assert_eq!(result, Ok(()));
+	}
+
+	#[test]
+	fn consensus() {
+		use Outcome::*;
+		assert_eq!([Ok, SomethingWentWrong, Ok].majority(), Some(Ok));
+		assert_eq!([Ok, SomethingWentWrong, IDontKnow].majority(), None);
+		assert_eq!([Ok, SomethingWentWrong].majority(), None);
+		assert_eq!(<[Outcome]>::majority(&[]), None);
+
+		assert_eq!([IDontKnow, IDontKnow].unanimous(), Some(IDontKnow));
+		assert_eq!([Ok, SomethingWentWrong, Ok].unanimous(), None);
+		assert_eq!(<[Outcome]>::unanimous(&[]), None);
+	}
+
+	#[test]
+	fn counts_on_given_threads() {
+		let x = process_stuff(0u32..1000);
+		for threads in [1, 2, 7, 2000] {
+			assert_eq!(x.counts_parallel(threads), x.counts());
+		}
+		assert_eq!(x[..0].counts_parallel(4), OutcomeSummary::default());
+	}
+
+	#[test]
+	fn summaries_to_csv() {
+		use crate::csv::ToCsv;
+
+		let summaries = process_stuff([3u32, 4, 5, 6, 9].into_iter()).summarize_chunks(2);
+		let mut csv = Vec::new();
+		summaries.to_csv(&mut csv).unwrap();
+		assert_eq!(
+			String::from_utf8(csv).unwrap(),
+			"index,ok,failed,unknown\n0,1,1,0\n1,1,0,1\n2,0,0,1\n"
+		);
+	}
 }
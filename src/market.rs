@@ -0,0 +1,185 @@
+//! In this module, energy is traded. Fuels have an ask price, plants offer their output at the
+//! marginal cost of producing it from their fuel, and a market is cleared by buying the cheapest
+//! offers first.
+
+use crate::h_advanced_traits::*;
+
+/// The ask price of a fuel, in cents per unit.
+pub trait FuelCost: Fuel {
+	const CENTS_PER_UNIT: u32;
+
+	/// The price of the energy contained in the fuel, in cents per thousand BTU.
+	fn cents_per_kbtu() -> u32 {
+		let density: BTU = Self::energy_density().into();
+		(Self::CENTS_PER_UNIT as u64 * 1000 / density.max(1) as u64) as u32
+	}
+}
+
+impl FuelCost for Diesel {
+	const CENTS_PER_UNIT: u32 = 300;
+}
+impl FuelCost for Coal {
+	const CENTS_PER_UNIT: u32 = 250;
+}
+impl FuelCost for NaturalGas {
+	const CENTS_PER_UNIT: u32 = 240;
+}
+impl FuelCost for Gasoline {
+	const CENTS_PER_UNIT: u32 = 352;
+}
+impl FuelCost for Uranium {
+	const CENTS_PER_UNIT: u32 = 500;
+}
+impl FuelCost for Hydrogen {
+	const CENTS_PER_UNIT: u32 = 1500;
+}
+impl FuelCost for Ethanol {
+	const CENTS_PER_UNIT: u32 = 320;
+}
+impl FuelCost for LithiumBattery {
+	const CENTS_PER_UNIT: u32 = 800;
+}
+impl FuelCost for Electricity {
+	const CENTS_PER_UNIT: u32 = 6;
+}
+
+/// A plant offering up to `capacity` BTU at `cents_per_kbtu`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Offer {
+	pub plant: String,
+	pub capacity: BTU,
+	pub cents_per_kbtu: u32,
+}
+
+impl Offer {
+	pub fn new(plant: impl Into<String>, capacity: BTU, cents_per_kbtu: u32) -> Self {
+		Self {
+			plant: plant.into(),
+			capacity,
+			cents_per_kbtu,
+		}
+	}
+
+	/// An offer at the marginal cost of `provider`: the price of `F`, divided by the efficiency
+	/// the provider would currently apply. A provider with no efficiency at all is priced out.
+	pub fn for_provider<F: FuelCost>(
+		plant: impl Into<String>,
+		provider: &impl ProvideEnergy<F>,
+		capacity: BTU,
+	) -> Self {
+		let cents_per_kbtu = match provider.current_efficiency().min(100) {
+			0 => u32::MAX,
+			efficiency => (F::cents_per_kbtu() as u64 * 100 / efficiency as u64) as u32,
+		};
+		Self::new(plant, capacity, cents_per_kbtu)
+	}
+}
+
+/// The outcome of [`clear`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MarketReport {
+	/// The price paid for all of the energy bought, i.e. that of the most expensive offer needed.
+	/// `None` if nothing was bought.
+	pub cleared_price: Option<u32>,
+	/// The energy bought from each plant, cheapest first.
+	pub dispatch: Vec<(String, BTU)>,
+	/// The part of the demand that no offer could cover.
+	pub unserved: BTU,
+}
+
+impl MarketReport {
+	/// The energy bought in total.
+	pub fn supplied(&self) -> BTU {
+		self.dispatch.iter().map(|(_, energy)| energy).sum()
+	}
+
+	/// The cost of all of the energy bought at the cleared price, in cents.
+	pub fn total_cost(&self) -> u64 {
+		let price = self.cleared_price.unwrap_or(0) as u64;
+		self.supplied() as u64 * price / 1000
+	}
+}
+
+/// Match `demand` to the cheapest `offers`. Offers at the same price are bought in the order they
+/// were given, and offers priced out at `u32::MAX` are never bought.
+///
+/// ```
+/// use pba_qualifier_exam::market::*;
+///
+/// # fn main() {
+/// let offers = [Offer::new("coal", 100, 1000), Offer::new("gas", 100, 800)];
+/// let report = clear(150, &offers);
+/// assert_eq!(report.cleared_price, Some(1000));
+/// assert_eq!(report.dispatch, [("gas".to_string(), 100), ("coal".to_string(), 50)]);
+/// # }
+/// ```
+pub fn clear(demand: BTU, offers: &[Offer]) -> MarketReport {
+	let mut merit_order = offers.iter().collect::<Vec<_>>();
+	merit_order.sort_by_key(|o| o.cents_per_kbtu);
+
+	let mut report = MarketReport {
+		unserved: demand,
+		..Default::default()
+	};
+	for offer in merit_order {
+		if report.unserved == 0 || offer.cents_per_kbtu == u32::MAX {
+			break;
+		}
+		let bought = offer.capacity.min(report.unserved);
+		if bought == 0 {
+			continue;
+		}
+		report.unserved -= bought;
+		report.cleared_price = Some(offer.cents_per_kbtu);
+		report.dispatch.push((offer.plant.clone(), bought));
+	}
+	report
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn marginal_costs() {
+		assert_eq!(Uranium::cents_per_kbtu(), 500);
+		assert_eq!(Diesel::cents_per_kbtu(), 3000);
+
+		let nuclear = Offer::for_provider::<Uranium>("nuclear", &NuclearReactor, 1000);
+		assert_eq!(nuclear.cents_per_kbtu, 505);
+		let gas = Offer::for_provider::<NaturalGas>("gas", &OmniGenerator::<80>, 400);
+		assert_eq!(gas.cents_per_kbtu, 2500);
+		let off = Offer::for_provider::<Coal>("off", &RuntimeGenerator::new(0), 400);
+		assert_eq!(off.cents_per_kbtu, u32::MAX);
+	}
+
+	#[test]
+	fn clearing_in_merit_order() {
+		let offers = [
+			Offer::for_provider::<Diesel>("diesel", &RuntimeGenerator::new(100), 1000),
+			Offer::for_provider::<Uranium>("nuclear", &NuclearReactor, 1000),
+			Offer::for_provider::<NaturalGas>("gas", &OmniGenerator::<80>, 400),
+		];
+
+		let report = clear(1500, &offers);
+		let dispatch = report
+			.dispatch
+			.iter()
+			.map(|(plant, energy)| (plant.as_str(), *energy))
+			.collect::<Vec<_>>();
+		assert_eq!(dispatch, [("nuclear", 1000), ("gas", 400), ("diesel", 100)]);
+		assert_eq!((report.cleared_price, report.unserved), (Some(3000), 0));
+		assert_eq!(report.total_cost(), 4500);
+
+		let report = clear(3000, &offers);
+		assert_eq!((report.supplied(), report.unserved), (2400, 600));
+
+		let off = Offer::for_provider::<Coal>("off", &RuntimeGenerator::new(0), 400);
+		let report = clear(3000, &[offers[0].clone(), off]);
+		assert_eq!(report.dispatch, [("diesel".to_string(), 1000)]);
+		assert_eq!((report.cleared_price, report.unserved), (Some(3000), 2000));
+
+		let report = clear(0, &offers);
+		assert_eq!(report, MarketReport::default());
+	}
+}
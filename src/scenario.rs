@@ -0,0 +1,780 @@
+//! In this module, a [`Simulation`] is configured from a scenario file rather than in code, such
+//! that it can be changed without recompiling.
+//!
+//! Scenarios are written in a small subset of TOML: comments, `[table]` and `[[array]]` headers,
+//! and `key = value` pairs whose values are integers or double-quoted strings without escapes.
+//! Other TOML features, and other formats such as JSON, are not supported.
+//!
+//! ```toml
+//! strategy = "least-cost"          # or "merit-order" (the default), "least-carbon", "round-robin"
+//! ticks = 168                      # optional, the ticks to run
+//!
+//! [[fuel]]
+//! name = "Diesel"                  # the built-in fuels need nothing else
+//! stock = 500
+//!
+//! [[fuel]]
+//! name = "biogas"
+//! density = 90                     # BTU per unit
+//! cost = 2500                      # cents per thousand BTU, optional
+//! carbon = 10                      # grams of CO2 per thousand BTU, optional
+//! stock = 200
+//!
+//! [[plant]]
+//! name = "diesel-1"
+//! fuel = "Diesel"
+//! efficiency = 80                  # percent
+//! max_fuel = 10                    # units per tick
+//!
+//! [storage]                        # optional
+//! capacity = 1000
+//! efficiency = 90
+//!
+//! [emissions_cap]                  # optional
+//! grams = 50000                    # of CO2
+//! period = 24                      # ticks, or 0 for the whole run
+//!
+//! [[consumer]]
+//! profile = "diurnal"              # with base, amplitude and period
+//! base = 500
+//! amplitude = 200
+//! period = 24
+//!
+//! [[consumer]]
+//! profile = "step"                 # with before, after and at; or "constant" with demand
+//! before = 0
+//! after = 100
+//! at = 50
+//! ```
+
+use crate::grid::*;
+use crate::h_advanced_traits::*;
+use crate::market::FuelCost;
+
+/// The order in which the plants of a scenario are dispatched, see [`DispatchStrategy`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StrategySpec {
+	#[default]
+	MeritOrder,
+	LeastCost,
+	LeastCarbon,
+	RoundRobin,
+}
+
+/// A plant of a scenario, run by a [`RuntimeGenerator`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlantSpec {
+	pub name: String,
+	/// The name of one of the fuels of the scenario.
+	pub fuel: String,
+	pub efficiency: u8,
+	pub max_fuel: u32,
+}
+
+/// A consumer of a scenario.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsumerSpec {
+	Constant(Constant),
+	Diurnal(Diurnal),
+	Step(Step),
+}
+
+/// The ticks a scenario runs for, unless it says otherwise.
+pub const DEFAULT_TICKS: u64 = 100;
+
+/// Everything needed to build a [`Simulation`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scenario {
+	/// The ticks to run, [`DEFAULT_TICKS`] unless given.
+	pub ticks: Option<u64>,
+	/// The fuels and their initial stock.
+	pub fuels: Vec<(DynamicFuel, u32)>,
+	pub plants: Vec<PlantSpec>,
+	pub storage: Option<Storage>,
+	pub emissions_cap: Option<EmissionsCap>,
+	pub consumers: Vec<ConsumerSpec>,
+	pub strategy: StrategySpec,
+}
+
+/// Why a scenario could not be loaded or built.
+#[derive(Debug)]
+pub enum ScenarioError {
+	Io(std::io::Error),
+	/// The file is not valid, at the given line.
+	Parse {
+		line: usize,
+		message: String,
+	},
+	/// The scenario does not hold together, e.g. a plant burns a fuel that is not declared.
+	Invalid(String),
+	/// The simulation panicked while running, with the given message.
+	Panicked(String),
+}
+
+impl std::fmt::Display for ScenarioError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ScenarioError::Io(error) => write!(f, "cannot read scenario: {error}"),
+			ScenarioError::Parse { line, message } => write!(f, "line {line}: {message}"),
+			ScenarioError::Invalid(message) => write!(f, "invalid scenario: {message}"),
+			ScenarioError::Panicked(message) => write!(f, "simulation panicked: {message}"),
+		}
+	}
+}
+
+impl std::error::Error for ScenarioError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ScenarioError::Io(error) => Some(error),
+			ScenarioError::Parse { .. }
+			| ScenarioError::Invalid(_)
+			| ScenarioError::Panicked(_) => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for ScenarioError {
+	fn from(error: std::io::Error) -> Self {
+		ScenarioError::Io(error)
+	}
+}
+
+fn parse_error<T>(line: usize, message: impl Into<String>) -> Result<T, ScenarioError> {
+	Err(ScenarioError::Parse {
+		line,
+		message: message.into(),
+	})
+}
+
+impl Scenario {
+	/// Read and parse the scenario file at `path`.
+	pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, ScenarioError> {
+		Self::from_toml(&std::fs::read_to_string(path)?)
+	}
+
+	/// Parse a scenario, see the [module documentation](self) for its format.
+	pub fn from_toml(source: &str) -> Result<Self, ScenarioError> {
+		let mut scenario = Scenario::default();
+		for mut table in parse_tables(source)? {
+			match table.name.as_str() {
+				"" => {
+					if let Some((strategy, line)) = table.take_str("strategy")? {
+						scenario.strategy = match strategy.as_str() {
+							"merit-order" => StrategySpec::MeritOrder,
+							"least-cost" => StrategySpec::LeastCost,
+							"least-carbon" => StrategySpec::LeastCarbon,
+							"round-robin" => StrategySpec::RoundRobin,
+							other => {
+								return parse_error(line, format!("unknown strategy `{other}`"))
+							}
+						};
+					}
+					scenario.ticks = table.take_int("ticks")?;
+				}
+				"fuel" => {
+					let fuel = table.fuel(&scenario.fuels)?;
+					scenario.fuels.push(fuel);
+				}
+				"plant" => scenario.plants.push(table.plant(&scenario.fuels)?),
+				"storage" => {
+					let capacity = table.require_int("capacity")?;
+					let efficiency = table.require_int("efficiency")?;
+					scenario.storage = Some(Storage::new(capacity, Percent::new(efficiency)));
+				}
+				"emissions_cap" => {
+					scenario.emissions_cap = Some(EmissionsCap {
+						grams: table.require_int("grams")?,
+						period: table.require_int("period")?,
+					});
+				}
+				"consumer" => scenario.consumers.push(table.consumer()?),
+				other => return parse_error(table.line, format!("unknown table `{other}`")),
+			}
+			table.finish()?;
+		}
+		Ok(scenario)
+	}
+
+	/// Check that the scenario holds together, as [`Scenario::build`] does. Scenarios parsed with
+	/// [`Scenario::from_toml`] always do, but those put together in code may not.
+	pub fn validate(&self) -> Result<(), ScenarioError> {
+		for (index, (fuel, _)) in self.fuels.iter().enumerate() {
+			if self.fuels[..index]
+				.iter()
+				.any(|(f, _)| f.name() == fuel.name())
+			{
+				return Err(ScenarioError::Invalid(format!(
+					"fuel `{}` is declared twice",
+					fuel.name()
+				)));
+			}
+		}
+		self.plants
+			.iter()
+			.try_for_each(|plant| self.fuel_of(plant).map(drop))
+	}
+
+	/// The fuel burned by `plant`, which must be one of the fuels of the scenario.
+	fn fuel_of(&self, plant: &PlantSpec) -> Result<&DynamicFuel, ScenarioError> {
+		self.fuels
+			.iter()
+			.map(|(fuel, _)| fuel)
+			.find(|fuel| fuel.name() == plant.fuel)
+			.ok_or_else(|| {
+				ScenarioError::Invalid(format!(
+					"plant `{}` burns unknown fuel `{}`",
+					plant.name, plant.fuel
+				))
+			})
+	}
+
+	/// A simulation at its first tick, with the full initial stock of every fuel.
+	pub fn build(&self) -> Result<Simulation, ScenarioError> {
+		self.validate()?;
+		let mut grid = PowerGrid::new();
+		for (fuel, stock) in &self.fuels {
+			grid.stock(fuel.clone(), *stock);
+		}
+		for plant in &self.plants {
+			let fuel = self.fuel_of(plant)?;
+			grid = grid.with_plant(
+				plant.name.clone(),
+				RuntimeGenerator::new(plant.efficiency),
+				fuel.clone(),
+				plant.max_fuel,
+			);
+		}
+		if let Some(storage) = &self.storage {
+			grid = grid.with_storage(storage.clone());
+		}
+		if let Some(cap) = self.emissions_cap {
+			grid = grid.with_emissions_cap(cap);
+		}
+		grid = match self.strategy {
+			StrategySpec::MeritOrder => grid.with_strategy(MeritOrder),
+			StrategySpec::LeastCost => grid.with_strategy(LeastCost),
+			StrategySpec::LeastCarbon => grid.with_strategy(LeastCarbon),
+			StrategySpec::RoundRobin => grid.with_strategy(RoundRobin::default()),
+		};
+
+		let simulation = Simulation::new(grid);
+		#[cfg(feature = "snapshot")]
+		let simulation = simulation.with_scenario(self.clone());
+		let simulation = self
+			.consumers
+			.iter()
+			.fold(simulation, |simulation, consumer| match *consumer {
+				ConsumerSpec::Constant(c) => simulation.with_consumer(c),
+				ConsumerSpec::Diurnal(c) => simulation.with_consumer(c),
+				ConsumerSpec::Step(c) => simulation.with_consumer(c),
+			});
+		Ok(simulation)
+	}
+
+	/// Build the simulation, and run it for the ticks of the scenario.
+	pub fn run(&self) -> RunResult {
+		let mut simulation = self.build()?;
+		simulation.run(self.ticks.unwrap_or(DEFAULT_TICKS));
+		Ok(ScenarioRun {
+			history: simulation.history().to_vec(),
+			summary: simulation.summary(),
+		})
+	}
+}
+
+/// What running a [`Scenario`] produced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScenarioRun {
+	pub history: Vec<TickResult>,
+	pub summary: SimulationSummary,
+}
+
+/// The outcome of running a [`Scenario`].
+pub type RunResult = Result<ScenarioRun, ScenarioError>;
+
+/// Run independent `scenarios` on (at most) `threads` scoped threads, e.g. for parameter sweeps,
+/// and return their results in the order of the scenarios. A scenario that cannot be built, or
+/// that panics while running, does not keep the others from running.
+///
+/// Simulations are not [`Send`], since their plants need not be, so each one is built on the thread
+/// that runs it.
+///
+/// Panics if `threads` is zero.
+///
+/// ```
+/// use pba_qualifier_exam::scenario::*;
+///
+/// # fn main() {
+/// let sweep = (1..=4)
+///     .map(|stock| {
+///         let source = format!(
+///             "ticks = 10\n[[fuel]]\nname = \"Coal\"\nstock = {stock}\n\
+///              [[plant]]\nname = \"coal\"\nfuel = \"Coal\"\nefficiency = 50\nmax_fuel = 1"
+///         );
+///         Scenario::from_toml(&source).unwrap()
+///     })
+///     .collect();
+///
+/// let results = run_many(sweep, 2);
+/// let ticks = results
+///     .iter()
+///     .map(|r| r.as_ref().unwrap().summary.ticks)
+///     .collect::<Vec<_>>();
+/// assert_eq!(ticks, [10, 10, 10, 10]);
+/// # }
+/// ```
+pub fn run_many(scenarios: Vec<Scenario>, threads: usize) -> Vec<RunResult> {
+	assert!(threads > 0, "need at least one thread");
+	let chunk_size = ((scenarios.len() + threads - 1) / threads).max(1);
+
+	std::thread::scope(|s| {
+		let handles = scenarios
+			.chunks(chunk_size)
+			.map(|chunk| {
+				s.spawn(move || {
+					chunk
+						.iter()
+						.map(|scenario| isolated(|| scenario.run()))
+						.collect::<Vec<_>>()
+				})
+			})
+			.collect::<Vec<_>>();
+		handles
+			.into_iter()
+			.flat_map(|h| h.join().expect("panics are caught per scenario"))
+			.collect()
+	})
+}
+
+/// Run `run`, turning a panic into a [`ScenarioError::Panicked`].
+fn isolated(run: impl FnOnce() -> RunResult) -> RunResult {
+	std::panic::catch_unwind(std::panic::AssertUnwindSafe(run)).unwrap_or_else(|payload| {
+		let message = match payload.downcast_ref::<&str>() {
+			Some(message) => message.to_string(),
+			None => payload
+				.downcast_ref::<String>()
+				.cloned()
+				.unwrap_or_else(|| "unknown panic".to_string()),
+		};
+		Err(ScenarioError::Panicked(message))
+	})
+}
+
+/// The runtime description of a built-in fuel, with its price and carbon intensity.
+fn known<F: FuelCost + CarbonIntensity>() -> DynamicFuel {
+	DynamicFuel::of::<F>()
+		.with_cost(F::cents_per_kbtu())
+		.with_carbon_intensity(F::GRAMS_PER_KBTU)
+}
+
+fn builtin_fuel(name: &str) -> Option<DynamicFuel> {
+	let fuels = [
+		known::<Diesel>(),
+		known::<LithiumBattery>(),
+		known::<Uranium>(),
+		known::<Ethanol>(),
+		known::<Hydrogen>(),
+		known::<Coal>(),
+		known::<NaturalGas>(),
+		known::<Gasoline>(),
+		known::<Electricity>(),
+	];
+	fuels.into_iter().find(|fuel| fuel.name() == name)
+}
+
+enum Value {
+	Int(u64),
+	Str(String),
+}
+
+/// A `[table]` or `[[array]]` element, or the top-level keys if `name` is empty.
+struct Table {
+	name: String,
+	line: usize,
+	/// The keys not taken yet, with their value and line.
+	entries: Vec<(String, Value, usize)>,
+}
+
+impl Table {
+	fn take(&mut self, key: &str) -> Option<(Value, usize)> {
+		let index = self.entries.iter().position(|(k, _, _)| k == key)?;
+		let (_, value, line) = self.entries.remove(index);
+		Some((value, line))
+	}
+
+	fn take_str(&mut self, key: &str) -> Result<Option<(String, usize)>, ScenarioError> {
+		match self.take(key) {
+			None => Ok(None),
+			Some((Value::Str(s), line)) => Ok(Some((s, line))),
+			Some((Value::Int(_), line)) => parse_error(line, format!("`{key}` must be a string")),
+		}
+	}
+
+	fn take_int<T: TryFrom<u64>>(&mut self, key: &str) -> Result<Option<T>, ScenarioError> {
+		match self.take(key) {
+			None => Ok(None),
+			Some((Value::Int(i), line)) => match T::try_from(i) {
+				Ok(i) => Ok(Some(i)),
+				Err(_) => parse_error(line, format!("`{key}` is out of range")),
+			},
+			Some((Value::Str(_), line)) => parse_error(line, format!("`{key}` must be an integer")),
+		}
+	}
+
+	fn require_str(&mut self, key: &str) -> Result<(String, usize), ScenarioError> {
+		let missing = format!("`{key}` is missing in `{}`", self.name);
+		self.take_str(key)?
+			.map_or_else(|| parse_error(self.line, missing), Ok)
+	}
+
+	fn require_int<T: TryFrom<u64>>(&mut self, key: &str) -> Result<T, ScenarioError> {
+		let missing = format!("`{key}` is missing in `{}`", self.name);
+		self.take_int(key)?
+			.map_or_else(|| parse_error(self.line, missing), Ok)
+	}
+
+	/// Fails on the first key that was not taken.
+	fn finish(self) -> Result<(), ScenarioError> {
+		match self.entries.first() {
+			Some((key, _, line)) => parse_error(*line, format!("unknown key `{key}`")),
+			None => Ok(()),
+		}
+	}
+
+	fn fuel(&mut self, fuels: &[(DynamicFuel, u32)]) -> Result<(DynamicFuel, u32), ScenarioError> {
+		let (name, line) = self.require_str("name")?;
+		if fuels.iter().any(|(f, _)| f.name() == name) {
+			return parse_error(line, format!("fuel `{name}` is declared twice"));
+		}
+		let mut fuel = match (self.take_int("density")?, builtin_fuel(&name)) {
+			(Some(density), _) => DynamicFuel::new(name, density),
+			(None, Some(fuel)) => fuel,
+			(None, None) => {
+				return parse_error(self.line, format!("`density` is missing for `{name}`"))
+			}
+		};
+		if let Some(cost) = self.take_int("cost")? {
+			fuel = fuel.with_cost(cost);
+		}
+		if let Some(carbon) = self.take_int("carbon")? {
+			fuel = fuel.with_carbon_intensity(carbon);
+		}
+		Ok((fuel, self.take_int("stock")?.unwrap_or(0)))
+	}
+
+	fn plant(&mut self, fuels: &[(DynamicFuel, u32)]) -> Result<PlantSpec, ScenarioError> {
+		let (name, _) = self.require_str("name")?;
+		let (fuel, line) = self.require_str("fuel")?;
+		if !fuels.iter().any(|(f, _)| f.name() == fuel) {
+			return parse_error(line, format!("unknown fuel `{fuel}`, declare it first"));
+		}
+		Ok(PlantSpec {
+			name,
+			fuel,
+			efficiency: self.require_int("efficiency")?,
+			max_fuel: self.require_int("max_fuel")?,
+		})
+	}
+
+	fn consumer(&mut self) -> Result<ConsumerSpec, ScenarioError> {
+		let (profile, line) = self.require_str("profile")?;
+		Ok(match profile.as_str() {
+			"constant" => ConsumerSpec::Constant(Constant(self.require_int("demand")?)),
+			"diurnal" => ConsumerSpec::Diurnal(Diurnal {
+				base: self.require_int("base")?,
+				amplitude: self.require_int("amplitude")?,
+				period: self.require_int("period")?,
+			}),
+			"step" => ConsumerSpec::Step(Step {
+				before: self.require_int("before")?,
+				after: self.require_int("after")?,
+				at: self.require_int("at")?,
+			}),
+			other => return parse_error(line, format!("unknown profile `{other}`")),
+		})
+	}
+}
+
+/// Split `source` into its tables, in order, starting with the top-level keys.
+fn parse_tables(source: &str) -> Result<Vec<Table>, ScenarioError> {
+	let mut tables = vec![Table {
+		name: String::new(),
+		line: 0,
+		entries: Vec::new(),
+	}];
+	let mut singles = Vec::new();
+
+	for (index, raw) in source.lines().enumerate() {
+		let line = index + 1;
+		let text = strip_comment(raw).trim();
+		if text.is_empty() {
+			continue;
+		}
+
+		let header = match text.strip_prefix("[[").and_then(|t| t.strip_suffix("]]")) {
+			Some(name) => Some(name.trim()),
+			None => match text
+				.strip_prefix('[')
+				.and_then(|t| t.strip_suffix(']'))
+				.map(str::trim)
+			{
+				Some(name) if singles.contains(&name) => {
+					return parse_error(line, format!("duplicate table `{name}`"))
+				}
+				Some(name) => {
+					singles.push(name);
+					Some(name)
+				}
+				None => None,
+			},
+		};
+		if let Some(name) = header {
+			tables.push(Table {
+				name: name.to_string(),
+				line,
+				entries: Vec::new(),
+			});
+			continue;
+		}
+
+		let Some((key, value)) = text.split_once('=') else {
+			return parse_error(line, "expected `key = value` or a table header");
+		};
+		let key = key.trim();
+		let value = parse_value(value.trim()).ok_or(ScenarioError::Parse {
+			line,
+			message: format!("invalid value for `{key}`"),
+		})?;
+		let table = tables
+			.last_mut()
+			.expect("there is always the top-level table");
+		if table.entries.iter().any(|(k, _, _)| k == key) {
+			return parse_error(line, format!("duplicate key `{key}`"));
+		}
+		table.entries.push((key.to_string(), value, line));
+	}
+	Ok(tables)
+}
+
+/// `line` up to its comment, if any. A `#` within a string does not start a comment.
+fn strip_comment(line: &str) -> &str {
+	let mut in_string = false;
+	for (index, c) in line.char_indices() {
+		match c {
+			'"' => in_string = !in_string,
+			'#' if !in_string => return &line[..index],
+			_ => {}
+		}
+	}
+	line
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+	if let Some(s) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+		return (!s.contains('"')).then(|| Value::Str(s.to_string()));
+	}
+	value.replace('_', "").parse().ok().map(Value::Int)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SCENARIO: &str = r#"
+		# A small town.
+		strategy = "least-cost"
+
+		[[fuel]]
+		name = "Diesel"
+		stock = 1_000
+
+		[[fuel]]
+		name = "biogas"
+		density = 90
+		cost = 2500 # cheaper than diesel
+		stock = 10
+
+		[[plant]]
+		name = "diesel"
+		fuel = "Diesel"
+		efficiency = 100
+		max_fuel = 10
+
+		[[plant]]
+		name = "digester"
+		fuel = "biogas"
+		efficiency = 100
+		max_fuel = 1
+
+		[storage]
+		capacity = 100
+		efficiency = 50
+
+		[emissions_cap]
+		grams = 1_000_000
+		period = 0
+
+		[[consumer]]
+		profile = "constant"
+		demand = 120
+
+		[[consumer]]
+		profile = "step"
+		before = 0
+		after = 500
+		at = 2
+	"#;
+
+	#[test]
+	fn load_and_build() {
+		let scenario = Scenario::from_toml(SCENARIO).unwrap();
+		assert_eq!(scenario.strategy, StrategySpec::LeastCost);
+		assert_eq!(scenario.fuels[0].0.cents_per_kbtu(), 3000);
+		assert_eq!(
+			scenario.fuels[1],
+			(DynamicFuel::new("biogas", 90).with_cost(2500), 10)
+		);
+		assert_eq!(scenario.plants[1].max_fuel, 1);
+		assert_eq!(scenario.storage, Some(Storage::new(100, Percent::new(50))));
+		assert_eq!(scenario.emissions_cap.unwrap().grams, 1_000_000);
+		assert_eq!(scenario.consumers.len(), 2);
+
+		let mut simulation = scenario.build().unwrap();
+		let report = &simulation.run(3)[2].report;
+		assert_eq!(report.demand, 620);
+		// The digester is cheaper, so it runs first.
+		assert_eq!(report.plants[0].plant, "digester");
+		assert_eq!(report.supplied(), 90 + 600);
+		assert_eq!(simulation.grid().inventory("biogas"), 7);
+	}
+
+	#[test]
+	fn running_many_scenarios() {
+		let base = Scenario::from_toml(SCENARIO).unwrap();
+		assert_eq!(base.ticks, None);
+		let scenarios = [None, Some(5), Some(0)]
+			.into_iter()
+			.map(|ticks| Scenario {
+				ticks,
+				..base.clone()
+			})
+			.collect::<Vec<_>>();
+
+		let results = run_many(scenarios.clone(), 2)
+			.into_iter()
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap();
+		let ticks = results.iter().map(|r| r.summary.ticks).collect::<Vec<_>>();
+		assert_eq!(ticks, [DEFAULT_TICKS, 5, 0]);
+		assert_eq!(
+			results,
+			scenarios
+				.iter()
+				.map(|s| s.run().unwrap())
+				.collect::<Vec<_>>()
+		);
+		assert_eq!(run_many(scenarios, 8).len(), 3);
+		assert!(run_many(Vec::new(), 1).is_empty());
+
+		let scenario = Scenario::from_toml("ticks = 24").unwrap();
+		assert_eq!(scenario.ticks, Some(24));
+	}
+
+	#[test]
+	fn scenarios_built_in_code_are_validated() {
+		let base = Scenario::from_toml(SCENARIO).unwrap();
+		assert!(base.validate().is_ok());
+		let mut broken = base.clone();
+		broken.plants[0].fuel = "peat".to_string();
+
+		let error = broken.validate().unwrap_err().to_string();
+		assert_eq!(
+			error,
+			"invalid scenario: plant `diesel` burns unknown fuel `peat`"
+		);
+		assert!(broken.build().is_err());
+
+		let mut twice = base.clone();
+		twice.fuels.push(base.fuels[0].clone());
+		assert_eq!(
+			twice.validate().unwrap_err().to_string(),
+			"invalid scenario: fuel `Diesel` is declared twice"
+		);
+
+		let results = run_many(vec![broken, base], 2);
+		assert!(results[0].is_err());
+		assert!(results[1].is_ok());
+	}
+
+	#[test]
+	fn comments_stop_outside_strings() {
+		let scenario = Scenario::from_toml(
+			"[[ fuel ]] # padded\nname = \"gas #2\" # named\ndensity = 90\nstock = 5",
+		)
+		.unwrap();
+		assert_eq!(scenario.fuels[0].0.name(), "gas #2");
+		assert_eq!(scenario.fuels[0].1, 5);
+	}
+
+	#[test]
+	fn panicking_runs_are_isolated() {
+		let error = isolated(|| panic!("overflow")).unwrap_err();
+		assert_eq!(error.to_string(), "simulation panicked: overflow");
+
+		let error = isolated(|| panic!("{} overflows", "demand")).unwrap_err();
+		assert_eq!(error.to_string(), "simulation panicked: demand overflows");
+
+		let scenario = Scenario::from_toml(SCENARIO).unwrap();
+		assert_eq!(
+			isolated(|| scenario.run()).unwrap(),
+			scenario.run().unwrap()
+		);
+	}
+
+	#[test]
+	fn invalid_scenarios() {
+		let error = |source: &str| Scenario::from_toml(source).unwrap_err().to_string();
+
+		assert_eq!(
+			error("strategy = \"cheapest\""),
+			"line 1: unknown strategy `cheapest`"
+		);
+		assert_eq!(
+			error("[[fuel]]\nname = \"peat\""),
+			"line 1: `density` is missing for `peat`"
+		);
+		assert_eq!(
+			error("[[plant]]\nname = \"x\"\nfuel = \"Coal\""),
+			"line 3: unknown fuel `Coal`, declare it first"
+		);
+		assert_eq!(
+			error("[storage]\ncapacity = 1\nefficiency = 1\ncolour = \"red\""),
+			"line 4: unknown key `colour`"
+		);
+		assert_eq!(
+			error("[storage]\n[storage]"),
+			"line 2: duplicate table `storage`"
+		);
+		assert_eq!(
+			error("[[consumer]]\nprofile = \"constant\"\ndemand = -1"),
+			"line 3: invalid value for `demand`"
+		);
+		assert_eq!(
+			error("[[plant]]\nname = \"x\"\nfuel = 1"),
+			"line 3: `fuel` must be a string"
+		);
+		assert_eq!(error("[party]"), "line 1: unknown table `party`");
+		assert_eq!(
+			error("[[fuel]]\nname = \"Diesel\"\n[[ fuel ]]\nname = \"Diesel\""),
+			"line 4: fuel `Diesel` is declared twice"
+		);
+		assert_eq!(
+			error("[storage]\ncapacity = 1\nefficiency = 1\n[ storage ]"),
+			"line 4: duplicate table `storage`"
+		);
+		assert!(matches!(
+			Scenario::load("/does/not/exist.toml"),
+			Err(ScenarioError::Io(_))
+		));
+	}
+}